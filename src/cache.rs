@@ -0,0 +1,114 @@
+// This is free and unencumbered software released into the public domain.
+
+//! A cache of parsed module manifests, so that scripts invoking this program
+//! many times over don't pay for parsing every manifest on each invocation.
+//!
+//! The manifest directories are still listed on each invocation, so added
+//! and removed manifests are always picked up, but a manifest is only parsed
+//! again when its modification time or size changed.
+
+use asimov_env::paths::asimov_root;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::SystemTime,
+};
+
+/// The cached manifests, keyed by their paths.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ManifestCache {
+    /// The version of this program that wrote the cache, which is discarded
+    /// by any other version.
+    version: String,
+
+    entries: BTreeMap<PathBuf, CacheEntry>,
+
+    #[serde(skip)]
+    dirty: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CacheEntry {
+    modified: SystemTime,
+    len: u64,
+    manifest: serde_yml::Value,
+}
+
+impl ManifestCache {
+    /// Returns the path of the cache file.
+    pub fn path() -> PathBuf {
+        asimov_root().join("cache").join("manifests.json")
+    }
+
+    /// Loads the cache from the given path. A missing, unreadable or stale
+    /// cache file yields an empty cache.
+    pub fn load(path: &Path) -> Self {
+        std::fs::read(path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Self>(&bytes).ok())
+            .filter(|cache| cache.version == env!("CARGO_PKG_VERSION"))
+            .unwrap_or_else(|| Self {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                ..Default::default()
+            })
+    }
+
+    /// Saves the cache to the given path, if it changed since it was loaded,
+    /// dropping the entries for manifests that no longer exist.
+    pub fn save(&mut self, path: &Path) -> std::io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.entries.retain(|path, _| path.exists());
+
+        let json = serde_json::to_vec(self)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Concurrent writers, whether in this invocation or another, must never
+        // observe a partially written cache, nor write to the same temporary file:
+        static WRITERS: AtomicUsize = AtomicUsize::new(0);
+        let writer = WRITERS.fetch_add(1, Ordering::Relaxed);
+        let temp_path = path.with_extension(format!("json.{}.{}", std::process::id(), writer));
+        std::fs::write(&temp_path, json)?;
+        std::fs::rename(&temp_path, path)
+    }
+
+    /// Returns the cached manifest at the given path, unless the file was
+    /// modified since it was cached, in which case it's read anew.
+    pub fn get_or_read<E>(
+        &mut self,
+        path: &Path,
+        read: impl FnOnce(&Path) -> Result<serde_yml::Value, E>,
+    ) -> Result<serde_yml::Value, E> {
+        // Stat before reading, so that a concurrent modification can't leave
+        // a stale manifest cached:
+        let Some((modified, len)) = stat(path) else {
+            return read(path);
+        };
+        if let Some(entry) = self.entries.get(path) {
+            if entry.modified == modified && entry.len == len {
+                return Ok(entry.manifest.clone());
+            }
+        }
+
+        let manifest = read(path)?;
+        self.entries.insert(
+            path.to_path_buf(),
+            CacheEntry {
+                modified,
+                len,
+                manifest: manifest.clone(),
+            },
+        );
+        self.dirty = true;
+        Ok(manifest)
+    }
+}
+
+fn stat(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
@@ -1,5 +1,10 @@
 // This is free and unencumbered software released into the public domain.
 
+#[cfg(feature = "catalog")]
+mod catalog;
+#[cfg(feature = "catalog")]
+pub use catalog::*;
+
 mod external;
 pub use external::*;
 
@@ -18,3 +23,16 @@ pub use help_cmd::*;
 mod import;
 #[cfg(feature = "import")]
 pub use import::*;
+
+#[cfg(feature = "index")]
+mod index;
+#[cfg(feature = "index")]
+pub use index::*;
+
+#[cfg(feature = "module")]
+mod module;
+#[cfg(feature = "module")]
+pub use module::*;
+
+mod run;
+pub use run::*;
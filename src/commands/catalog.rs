@@ -0,0 +1,90 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    commands::{External, OutputStream},
+    shared::{build_resolver, module_args, normalize_input},
+    StandardOptions, SysexitsError,
+};
+use color_print::ceprintln;
+use miette::Result;
+use std::io::Write;
+
+/// Runs the `*-cataloger` module program resolved for each URL, which lists
+/// the resources behind the URL, one URL per line. The listing is printed as
+/// is, ready to be piped into `asimov fetch -` or `asimov import -`, or as a
+/// JSON array of URLs if `json` is `true`.
+pub fn catalog(
+    urls: &[String],
+    args: &[String],
+    json: bool,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let modules = build_resolver("cataloger").map_err(|e| {
+        ceprintln!("<s,r>error:</> failed to build a resolver: {e}");
+        SysexitsError::EX_UNAVAILABLE
+    })?;
+
+    let mut listing = Vec::new();
+    for url in urls {
+        let url = &normalize_input(url)?;
+
+        if flags.verbose > 1 {
+            ceprintln!("<s,c>»</> Cataloging `{}`...", url);
+        }
+
+        let Some(module) = modules.resolve(url)? else {
+            ceprintln!(
+                "<s,r>error:</> failed to find a module to catalog the URL: `{}`",
+                url
+            );
+            return Err(SysexitsError::EX_SOFTWARE);
+        };
+
+        let cmd = External {
+            is_debug: flags.debug,
+            verbosity: flags.verbose,
+            color: flags.color,
+            module: modules.get(&module),
+            ..Default::default()
+        };
+
+        let mut stdout = std::io::stdout();
+        let mut output = Vec::new();
+        let result = cmd.execute_streaming(
+            &format!("{}-cataloger", module),
+            &module_args(&modules, &module, args, url, flags),
+            |stream, chunk| match stream {
+                OutputStream::Stdout if json => output.extend_from_slice(chunk),
+                OutputStream::Stdout => {
+                    let _ = stdout.write_all(chunk).and_then(|_| stdout.flush());
+                }
+                OutputStream::Stderr => {
+                    let _ = std::io::stderr().write_all(chunk);
+                }
+            },
+        )?;
+        if result.code.is_failure() {
+            return Err(result.code);
+        }
+
+        listing.extend(
+            String::from_utf8_lossy(&output)
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(String::from),
+        );
+
+        if flags.verbose > 0 {
+            ceprintln!("<s,g>✓</> Cataloged `{}`.", url);
+        }
+    }
+
+    if json {
+        let json =
+            serde_json::to_string_pretty(&listing).map_err(|_| SysexitsError::EX_SOFTWARE)?;
+        println!("{}", json);
+    }
+
+    Ok(())
+}
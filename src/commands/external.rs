@@ -1,90 +1,311 @@
 // This is free and unencumbered software released into the public domain.
 
-use clientele::SysexitsError::{self, *};
-use std::process::{ExitStatus, Stdio};
+use clientele::{
+    crates::clap::ColorChoice,
+    SysexitsError::{self, *},
+};
+use std::{
+    io::{Read, Write},
+    process::{Command, ExitStatus, Stdio},
+    sync::mpsc::{self, SyncSender},
+};
 
-use crate::shared::locate_subcommand;
+use crate::limits::Limits;
+use crate::process::{exit_status, Job};
+use crate::sandbox::SandboxError;
+use crate::shared::{find_program_module, locate_subcommand, module_env, InstalledModule};
 use crate::Result;
 
 pub struct ExternalResult {
     /// Return code of the executed command.
     pub code: SysexitsError,
 
+    /// Shell-compatible exit status of the executed command, i.e., its exit
+    /// code or 128 plus the number of the signal that terminated it.
+    pub status: i32,
+
     /// If `pipe_output` is `true`, this field contains stdout, otherwise its None.
     pub stdout: Option<Vec<u8>>,
 
     /// If `pipe_output` is `true`, this field contains stderr, otherwise its None.
     pub stderr: Option<Vec<u8>>,
+
+    /// Whether `stdout` or `stderr` was cut short at `max_output` bytes.
+    pub truncated: bool,
+}
+
+/// The output stream a chunk of output was read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
 }
 
 /// Executes the given subcommand.
-pub struct External {
+///
+/// The subcommand inherits the environment of this process, extended with
+/// the variables documented at [`module_env`]. It's run in the sandbox (see
+/// [`crate::sandbox`]) if that's enabled.
+#[derive(Debug, Default)]
+pub struct External<'a> {
     pub is_debug: bool,
     pub pipe_output: bool,
+
+    /// Verbosity level passed on to the subcommand as `ASIMOV_VERBOSE`.
+    pub verbosity: u8,
+
+    /// Color mode passed on to the subcommand as `ASIMOV_COLOR`.
+    pub color: ColorChoice,
+
+    /// On Unix, replace this process with the subcommand instead of waiting
+    /// for it to exit, unless `pipe_output` is `true`. This way, terminal
+    /// handling, job control, signals and exit status behave exactly as if
+    /// the subcommand was run directly.
+    pub exec: bool,
+
+    /// If `pipe_output` is `true`, also copy the output to our own stdout
+    /// and stderr as it arrives.
+    pub tee: bool,
+
+    /// If `pipe_output` is `true`, capture at most this many bytes of each
+    /// output stream. Any further output is still copied if `tee` is `true`.
+    pub max_output: Option<usize>,
+
+    /// Additional environment variables for the subcommand.
+    pub env: Vec<(String, String)>,
+
+    /// The module providing the subcommand, if already resolved, so that it
+    /// needn't be looked up again.
+    pub module: Option<&'a InstalledModule>,
 }
 
-impl External {
+impl External<'_> {
     pub fn execute(&self, cmd: &str, args: &[String]) -> Result<ExternalResult> {
+        if !self.pipe_output {
+            let (mut command, limits) = self.command(cmd, args)?;
+            command.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+
+            #[cfg(unix)]
+            if self.exec {
+                use std::os::unix::process::CommandExt;
+
+                // This only returns on failure:
+                return Err(self.failed(command.exec()));
+            }
+
+            let status = Job::spawn(&mut command, None)
+                .and_then(Job::wait)
+                .map_err(|error| self.failed(error))?;
+            return Ok(self.result(status, &limits));
+        }
+
+        let mut stdout = Capture::new(self.max_output);
+        let mut stderr = Capture::new(self.max_output);
+        let mut result = self.execute_streaming(cmd, args, |stream, chunk| {
+            if self.tee {
+                let _ = match stream {
+                    OutputStream::Stdout => std::io::stdout().write_all(chunk),
+                    OutputStream::Stderr => std::io::stderr().write_all(chunk),
+                };
+            }
+            match stream {
+                OutputStream::Stdout => stdout.push(chunk),
+                OutputStream::Stderr => stderr.push(chunk),
+            }
+        })?;
+
+        result.truncated = stdout.truncated || stderr.truncated;
+        result.stdout = Some(stdout.buffer);
+        result.stderr = Some(stderr.buffer);
+        Ok(result)
+    }
+
+    /// Executes the given subcommand with piped output, passing each chunk
+    /// of output to `on_output` as soon as it has been read.
+    ///
+    /// The `stdout` and `stderr` fields of the result are always `None`.
+    pub fn execute_streaming(
+        &self,
+        cmd: &str,
+        args: &[String],
+        mut on_output: impl FnMut(OutputStream, &[u8]),
+    ) -> Result<ExternalResult> {
+        let (mut command, limits) = self.command(cmd, args)?;
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+        let mut job = Job::spawn(&mut command, None).map_err(|error| self.failed(error))?;
+
+        // Read both pipes concurrently, so that neither of them fills up:
+        let (sender, receiver) = mpsc::sync_channel(16);
+        if let Some(pipe) = job.child.stdout.take() {
+            read_chunks(pipe, OutputStream::Stdout, sender.clone());
+        }
+        if let Some(pipe) = job.child.stderr.take() {
+            read_chunks(pipe, OutputStream::Stderr, sender.clone());
+        }
+        drop(sender);
+
+        for (stream, chunk) in receiver {
+            on_output(stream, &chunk);
+        }
+
+        let status = job.wait().map_err(|error| self.failed(error))?;
+        Ok(self.result(status, &limits))
+    }
+
+    /// Spawns the given subcommand with the given stdin and stdout, as a
+    /// job or as a member of the job led by `leader`. This is used to build
+    /// pipelines of subcommands.
+    pub(crate) fn spawn(
+        &self,
+        cmd: &str,
+        args: &[String],
+        stdin: Stdio,
+        stdout: Stdio,
+        leader: Option<&Job>,
+    ) -> Result<Job> {
+        let (mut command, limits) = self.command(cmd, args)?;
+        command.stdin(stdin).stdout(stdout).stderr(Stdio::inherit());
+        let mut job = Job::spawn(&mut command, leader).map_err(|error| self.failed(error))?;
+        job.limits = limits;
+        Ok(job)
+    }
+
+    /// Waits for a job spawned with [`External::spawn`] to exit.
+    pub(crate) fn wait(&self, job: Job) -> Result<ExternalResult> {
+        let limits = job.limits;
+        let status = job.wait().map_err(|error| self.failed(error))?;
+        Ok(self.result(status, &limits))
+    }
+
+    /// Prepares the process for the given subcommand, returning it along
+    /// with the resource limits it's run with.
+    pub(crate) fn command(&self, cmd: &str, args: &[String]) -> Result<(Command, Limits)> {
         // Locate the given subcommand:
         let cmd = locate_subcommand(cmd)?;
 
-        // Prepare the process:
-        let result: std::io::Result<(ExitStatus, Option<Vec<u8>>, Option<Vec<u8>>)> =
-            if self.pipe_output {
-                std::process::Command::new(&cmd.path)
-                    .args(args)
-                    .stdin(Stdio::inherit())
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::piped())
-                    .output()
-                    .map(|x| (x.status, Some(x.stdout), Some(x.stderr)))
-            } else {
-                std::process::Command::new(&cmd.path)
-                    .args(args)
-                    .stdin(Stdio::inherit())
-                    .stdout(Stdio::inherit())
-                    .stderr(Stdio::inherit())
-                    .status()
-                    .map(|x| (x, None, None))
-            };
-
-        match result {
-            Err(error) => {
+        let mut command = Command::new(&cmd.path);
+        command
+            .args(args)
+            .envs(module_env(self.is_debug, self.verbosity, self.color))
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::inherit());
+
+        // The subcommand's module, unless already resolved by the caller,
+        // which plain subcommands don't have:
+        let found;
+        let module = match self.module {
+            Some(module) => Ok(Some(module)),
+            None => {
+                found = find_program_module(&cmd.path);
+                found.as_ref().map(Option::as_ref)
+            }
+        };
+
+        // Programs not declared by a readable manifest have no limits of
+        // their own:
+        let limits = crate::limits::for_program(module.ok().flatten()).map_err(|e| {
+            eprintln!("{}: failed to determine resource limits: {}", "asimov", e);
+            EX_CONFIG
+        })?;
+        limits.apply(&mut command);
+
+        if crate::sandbox::is_enabled() {
+            module
+                .map_err(|e| SandboxError::Failed(cmd.path.clone(), e.to_string()))
+                .and_then(|module| crate::sandbox::sandbox(&mut command, &cmd.path, module))
+                .map_err(|e| {
+                    eprintln!("{}: refusing to run {}: {}", "asimov", cmd.name, e);
+                    match e {
+                        SandboxError::Unsupported(_) => EX_UNAVAILABLE,
+                        SandboxError::Failed(..) => EX_OSERR,
+                    }
+                })?;
+        }
+
+        Ok((command, limits))
+    }
+
+    fn failed(&self, error: std::io::Error) -> SysexitsError {
+        if self.is_debug {
+            eprintln!("{}: {}", "asimov", error);
+        }
+        EX_SOFTWARE
+    }
+
+    fn result(&self, status: ExitStatus, limits: &Limits) -> ExternalResult {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+
+            if let Some(signal) = status.signal() {
                 if self.is_debug {
-                    eprintln!("{}: {}", "asimov", error);
+                    eprintln!("{}: terminated by signal {}", "asimov", signal);
                 }
-                Err(EX_SOFTWARE)
             }
-            Ok(result) => {
-                #[cfg(unix)]
-                {
-                    use std::os::unix::process::ExitStatusExt;
-
-                    if let Some(signal) = result.0.signal() {
-                        if self.is_debug {
-                            eprintln!("{}: terminated by signal {}", "asimov", signal);
-                        }
-
-                        return Ok(ExternalResult {
-                            code: SysexitsError::try_from((signal | 0x80) & 0xff)
-                                .unwrap_or(EX_SOFTWARE),
-                            stdout: result.1,
-                            stderr: result.2,
-                        });
+        }
+
+        if let Some(limit) = crate::limits::exceeded(&status, limits) {
+            eprintln!("{}: the module program {}", "asimov", limit);
+        }
+
+        let status = exit_status(&status);
+        ExternalResult {
+            // Signals and other non-sysexits statuses map to EX_SOFTWARE.
+            code: SysexitsError::try_from(status).unwrap_or(EX_SOFTWARE),
+            status,
+            stdout: None,
+            stderr: None,
+            truncated: false,
+        }
+    }
+}
+
+/// Output captured up to an optional size limit.
+struct Capture {
+    buffer: Vec<u8>,
+    limit: Option<usize>,
+    truncated: bool,
+}
+
+impl Capture {
+    fn new(limit: Option<usize>) -> Self {
+        Self {
+            buffer: Vec::new(),
+            limit,
+            truncated: false,
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        let len = match self.limit {
+            Some(limit) => chunk.len().min(limit.saturating_sub(self.buffer.len())),
+            None => chunk.len(),
+        };
+        self.truncated |= len < chunk.len();
+        self.buffer.extend_from_slice(&chunk[..len]);
+    }
+}
+
+/// Sends the chunks read from the given pipe from a background thread.
+fn read_chunks(
+    mut pipe: impl Read + Send + 'static,
+    stream: OutputStream,
+    sender: SyncSender<(OutputStream, Vec<u8>)>,
+) {
+    std::thread::spawn(move || {
+        let mut buffer = [0; 8192];
+        loop {
+            match pipe.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(len) => {
+                    if sender.send((stream, buffer[..len].to_vec())).is_err() {
+                        break;
                     }
                 }
-
-                Ok(ExternalResult {
-                    // unwrap_or should never happen because we are handling signal above.
-                    code: result
-                        .0
-                        .code()
-                        .and_then(|code| SysexitsError::try_from(code).ok())
-                        .unwrap_or(EX_SOFTWARE),
-                    stdout: result.1,
-                    stderr: result.2,
-                })
+                Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
             }
         }
-    }
+    });
 }
@@ -1,49 +1,167 @@
 // This is free and unencumbered software released into the public domain.
 
-use crate::{commands::External, shared::build_resolver, StandardOptions, SysexitsError};
+use crate::{
+    commands::{
+        is_completed, open_journal, rate_limits, record, run_programs, throttle_host, with_retries,
+        BatchOptions, External, Verb,
+    },
+    shared::{build_resolver, module_args, normalize_input, read_urls},
+    StandardOptions, SysexitsError,
+};
 use color_print::ceprintln;
 use miette::Result;
+use std::process::Stdio;
 
-pub fn fetch(urls: &Vec<String>, flags: &StandardOptions) -> Result<(), SysexitsError> {
-    let resolver = build_resolver("fetcher").map_err(|e| {
+/// Runs the `*-fetcher` module program resolved for each URL, passing on
+/// the standard options (as declared by the module's manifest) and `args`,
+/// retrying as requested by `batch`.
+pub fn fetch(
+    urls: &[String],
+    args: &[String],
+    batch: &BatchOptions,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let verb = Verb {
+        base: "fetch",
+        gerund: "Fetching",
+        past: "Fetched",
+    };
+    run_programs("fetcher", &verb, urls, args, batch, flags)
+}
+
+/// Pipes the output of the `*-fetcher` module program resolved for each URL
+/// into the `*-importer` module program resolved for the same URL, passing
+/// `args` on to the latter. As with `asimov import --from-stdin`, the
+/// importer gets `-` in place of the URL, and the URL in the
+/// `ASIMOV_SOURCE_URL` environment variable. The whole pipeline is
+/// throttled per host (see [`crate::throttle`]), and is retried and
+/// journaled as requested by `batch`.
+#[cfg(feature = "import")]
+pub fn fetch_and_import(
+    urls: &[String],
+    args: &[String],
+    batch: &BatchOptions,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let fetchers = build_resolver("fetcher").map_err(|e| {
+        ceprintln!("<s,r>error:</> failed to build a resolver: {e}");
+        SysexitsError::EX_UNAVAILABLE
+    })?;
+    let importers = build_resolver("importer").map_err(|e| {
         ceprintln!("<s,r>error:</> failed to build a resolver: {e}");
         SysexitsError::EX_UNAVAILABLE
     })?;
 
-    for url in urls {
+    let verb = Verb {
+        base: "fetch and import",
+        gerund: "Fetching and importing",
+        past: "Fetched and imported",
+    };
+    let mut journal = open_journal(batch)?;
+
+    for url in &read_urls(urls)? {
+        let url = &normalize_input(url)?;
+
+        if is_completed(&journal, url, &verb, flags) {
+            continue;
+        }
+
         if flags.verbose > 1 {
-            ceprintln!("<s><c>»</></> Fetching `{}`...", url);
+            ceprintln!("<s,c>»</> {} `{}`...", verb.gerund, url);
         }
 
-        let modules = resolver.resolve(url)?;
+        let Some(fetcher) = fetchers.resolve(url)? else {
+            ceprintln!(
+                "<s,r>error:</> failed to find a module to fetch the URL: `{}`",
+                url
+            );
+            return Err(SysexitsError::EX_SOFTWARE);
+        };
+        let Some(importer) = importers.resolve(url)? else {
+            ceprintln!(
+                "<s,r>error:</> failed to find a module to import the URL: `{}`",
+                url
+            );
+            return Err(SysexitsError::EX_SOFTWARE);
+        };
 
-        match modules.first() {
-            Some(module) => {
-                let subcommand = format!("{}-fetcher", module.name);
+        let cmd = External {
+            is_debug: flags.debug,
+            verbosity: flags.verbose,
+            color: flags.color,
+            module: fetchers.get(&fetcher),
+            ..Default::default()
+        };
+        let import_cmd = External {
+            is_debug: flags.debug,
+            verbosity: flags.verbose,
+            color: flags.color,
+            env: vec![("ASIMOV_SOURCE_URL".to_string(), url.clone())],
+            module: importers.get(&importer),
+            ..Default::default()
+        };
 
-                let cmd = External {
-                    is_debug: flags.debug,
-                    pipe_output: false,
-                };
+        let fetch_args = module_args(&fetchers, &fetcher, &[], url, flags);
+        let import_args = module_args(&importers, &importer, args, "-", flags);
+        let is_retriable = |code| {
+            [(&fetchers, &fetcher), (&importers, &importer)]
+                .into_iter()
+                .filter_map(|(modules, name)| modules.get(name))
+                .any(|module| module.extensions.is_retriable(code))
+        };
+        let limits = rate_limits(
+            [fetchers.get(&fetcher), importers.get(&importer)]
+                .into_iter()
+                .flatten(),
+        )?;
+        let code = with_retries(batch, url, is_retriable, || {
+            let _permit = throttle_host(url, &limits);
+            let mut fetch = cmd.spawn(
+                &format!("{}-fetcher", fetcher),
+                &fetch_args,
+                Stdio::inherit(),
+                Stdio::piped(),
+                None,
+            )?;
+            let fetched = fetch.child.stdout.take().map(Stdio::from);
 
-                let code = cmd
-                    .execute(&subcommand, &[url.to_owned()])
-                    .map(|result| result.code)?;
-                if code.is_failure() {
+            let import = import_cmd.spawn(
+                &format!("{}-importer", importer),
+                &import_args,
+                fetched.unwrap_or_else(Stdio::null),
+                Stdio::inherit(),
+                Some(&fetch),
+            );
+            let import = match import {
+                Ok(import) => import,
+                Err(code) => {
+                    let _ = fetch.child.kill();
+                    let _ = cmd.wait(fetch);
                     return Err(code);
                 }
-            }
-            None => {
-                ceprintln!(
-                    "<s,r>error:</> failed to find a module to fetch the URL: `{}`",
-                    url
-                );
-                return Err(SysexitsError::EX_SOFTWARE);
-            }
+            };
+
+            // The job's leader, i.e., the fetcher, must be waited for last:
+            let imported = import_cmd.wait(import)?;
+            let fetched = cmd.wait(fetch)?;
+            Ok([fetched.code, imported.code]
+                .into_iter()
+                .find(|code| code.is_failure())
+                .unwrap_or(SysexitsError::EX_OK))
+        })
+        .unwrap_or_else(|code| code);
+        record(
+            &mut journal,
+            url,
+            &format!("{} | {}", fetcher, importer),
+            code,
+        )?;
+        if code.is_failure() {
+            return Err(code);
         }
 
         if flags.verbose > 0 {
-            ceprintln!("<s><g>✓</></> Fetched `{}`.", url);
+            ceprintln!("<s,g>✓</> {} `{}`.", verb.past, url);
         }
     }
 
@@ -5,6 +5,7 @@ use clientele::SysexitsError::{self, *};
 use rayon::prelude::*;
 use std::process::Stdio;
 
+use crate::commands::External;
 use crate::Result;
 
 pub struct CommandDescription {
@@ -59,13 +60,17 @@ impl Help {
         let commands = provider.get_commands();
 
         let start_time = std::time::Instant::now();
+        let external = External::default();
 
         commands
             .par_iter()
             .filter_map(|cmd| {
-                let Ok(mut child) = std::process::Command::new(&cmd.path)
-                    .args(["--help"])
-                    .stdin(Stdio::inherit())
+                // Programs are located, verified, and set up like any other
+                // subcommand, so those that may not be run are skipped:
+                let (mut command, _) = external
+                    .command(&cmd.name, &[String::from("--help")])
+                    .ok()?;
+                let Ok(mut child) = command
                     .stdout(Stdio::piped())
                     .stderr(Stdio::piped())
                     .spawn()
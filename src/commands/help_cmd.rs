@@ -1,9 +1,11 @@
 // This is free and unencumbered software released into the public domain.
 
-use clientele::SysexitsError::{self, *};
-use std::process::Stdio;
+use clientele::{
+    crates::clap::ColorChoice,
+    SysexitsError::{self, *},
+};
 
-use crate::shared::locate_subcommand;
+use crate::commands::External;
 use crate::Result;
 
 pub struct HelpCmdResult {
@@ -21,44 +23,34 @@ pub struct HelpCmdResult {
 /// Executes `help` command for the given subcommand.
 pub struct HelpCmd {
     pub is_debug: bool,
+
+    /// Color mode passed on to the subcommand as `ASIMOV_COLOR`.
+    pub color: ColorChoice,
 }
 
 impl HelpCmd {
     pub fn execute(&self, cmd: &str, args: &[String]) -> Result<HelpCmdResult> {
-        // Locate the given subcommand:
-        let cmd = locate_subcommand(cmd)?;
-
-        // Execute the `--help` command:
-        let output = std::process::Command::new(&cmd.path)
-            .args([&[String::from("--help")], args].concat())
-            .stdin(Stdio::inherit())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output();
-
-        match output {
-            Err(error) => {
-                if self.is_debug {
-                    eprintln!("{}: {}", "asimov", error);
-                }
-                Err(EX_SOFTWARE)
-            }
-            Ok(output) => match output.status.code() {
-                Some(code) if code == EX_OK.as_i32() => Ok(HelpCmdResult {
-                    success: true,
-                    code: EX_OK,
-                    output: output.stdout,
-                }),
-                _ => Ok(HelpCmdResult {
-                    success: false,
-                    code: output
-                        .status
-                        .code()
-                        .and_then(|code| SysexitsError::try_from(code).ok())
-                        .unwrap_or(EX_SOFTWARE),
-                    output: output.stderr,
-                }),
+        // Execute the `--help` command, which is located, verified, and run
+        // like any other subcommand:
+        let external = External {
+            is_debug: self.is_debug,
+            pipe_output: true,
+            color: self.color,
+            ..Default::default()
+        };
+        let result = external.execute(cmd, &[&[String::from("--help")], args].concat())?;
+
+        Ok(match result.code {
+            EX_OK => HelpCmdResult {
+                success: true,
+                code: EX_OK,
+                output: result.stdout.unwrap_or_default(),
+            },
+            code => HelpCmdResult {
+                success: false,
+                code,
+                output: result.stderr.unwrap_or_default(),
             },
-        }
+        })
     }
 }
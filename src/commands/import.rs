@@ -1,50 +1,94 @@
 // This is free and unencumbered software released into the public domain.
 
-use crate::{commands::External, shared::build_resolver, StandardOptions, SysexitsError};
+use crate::{
+    commands::{run_programs, BatchOptions, External, Verb},
+    shared::{build_resolver, is_media_type, module_args, normalize_input},
+    StandardOptions, SysexitsError,
+};
 use color_print::ceprintln;
 use miette::Result;
 
-pub fn import(urls: &Vec<String>, flags: &StandardOptions) -> Result<(), SysexitsError> {
-    let resolver = build_resolver("importer").map_err(|e| {
+/// Runs the `*-importer` module program resolved for each URL, passing on
+/// the standard options (as declared by the module's manifest) and `args`,
+/// retrying as requested by `batch`.
+pub fn import(
+    urls: &[String],
+    args: &[String],
+    batch: &BatchOptions,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let verb = Verb {
+        base: "import",
+        gerund: "Importing",
+        past: "Imported",
+    };
+    run_programs("importer", &verb, urls, args, batch, flags)
+}
+
+/// Runs the `*-importer` module program on data read from stdin, such as the
+/// output of `asimov fetch`. The `source` is either the URL the data was
+/// fetched from or its media type (e.g., `text/html`). Either way, the
+/// module gets `-` in place of a URL, and the URL in the `ASIMOV_SOURCE_URL`
+/// environment variable or the media type in the `ASIMOV_CONTENT_TYPE` one.
+pub fn import_stdin(
+    source: &str,
+    args: &[String],
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let modules = build_resolver("importer").map_err(|e| {
         ceprintln!("<s,r>error:</> failed to build a resolver: {e}");
         SysexitsError::EX_UNAVAILABLE
     })?;
 
-    for url in urls {
-        if flags.verbose > 1 {
-            ceprintln!("<s,c>»</> Importing `{}`...", url);
-        }
-
-        let modules = resolver.resolve(url)?;
-
-        match modules.first() {
-            Some(module) => {
-                let subcommand = format!("{}-importer", module.name);
-
-                let cmd = External {
-                    is_debug: flags.debug,
-                    pipe_output: false,
-                };
-
-                let code = cmd
-                    .execute(&subcommand, &[url.to_owned()])
-                    .map(|result| result.code)?;
-                if code.is_failure() {
-                    return Err(code);
-                }
-            }
-            None => {
-                ceprintln!(
-                    "<s,r>error:</> failed to find a module to import the URL: `{}`",
-                    url
-                );
-                return Err(SysexitsError::EX_SOFTWARE);
-            }
-        }
-
-        if flags.verbose > 0 {
-            ceprintln!("<s,g>✓</> Imported `{}`.", url);
-        }
+    let (module, env) = if is_media_type(source) {
+        (
+            modules
+                .find_by_media_type(source)
+                .map(|module| module.manifest.name.clone()),
+            ("ASIMOV_CONTENT_TYPE".to_string(), source.to_string()),
+        )
+    } else {
+        let url = normalize_input(source)?;
+        (
+            modules.resolve(&url)?,
+            ("ASIMOV_SOURCE_URL".to_string(), url),
+        )
+    };
+
+    let Some(module) = module else {
+        ceprintln!(
+            "<s,r>error:</> failed to find a module to import the data: `{}`",
+            source
+        );
+        return Err(SysexitsError::EX_SOFTWARE);
+    };
+
+    if flags.verbose > 1 {
+        ceprintln!("<s,c>»</> Importing `{}` from stdin...", source);
+    }
+
+    let cmd = External {
+        is_debug: flags.debug,
+        pipe_output: false,
+        verbosity: flags.verbose,
+        color: flags.color,
+        env: vec![env],
+        module: modules.get(&module),
+        ..Default::default()
+    };
+
+    let code = cmd
+        .execute(
+            &format!("{}-importer", module),
+            &module_args(&modules, &module, args, "-", flags),
+        )
+        .map(|result| result.code)?;
+    if code.is_failure() {
+        return Err(code);
+    }
+
+    if flags.verbose > 0 {
+        ceprintln!("<s,g>✓</> Imported `{}`.", source);
     }
 
     Ok(())
@@ -0,0 +1,29 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    commands::{run_programs, BatchOptions, Verb},
+    StandardOptions, SysexitsError,
+};
+use miette::Result;
+
+/// Runs the `*-indexer` module program resolved for each URL, passing on
+/// the standard options (as declared by the module's manifest) and `args`.
+pub fn index(
+    urls: &[String],
+    args: &[String],
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let verb = Verb {
+        base: "index",
+        gerund: "Indexing",
+        past: "Indexed",
+    };
+    run_programs(
+        "indexer",
+        &verb,
+        urls,
+        args,
+        &BatchOptions::default(),
+        flags,
+    )
+}
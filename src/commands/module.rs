@@ -0,0 +1,22 @@
+// This is free and unencumbered software released into the public domain.
+
+mod enable;
+pub use enable::*;
+
+mod freeze;
+pub use freeze::*;
+
+mod install;
+pub use install::*;
+
+mod list;
+pub use list::*;
+
+mod search;
+pub use search::*;
+
+mod sync;
+pub use sync::*;
+
+mod uninstall;
+pub use uninstall::*;
@@ -0,0 +1,59 @@
+// This is free and unencumbered software released into the public domain.
+
+use super::find_installed;
+use crate::{shared::module_dir, StandardOptions, SysexitsError};
+use color_print::ceprintln;
+use miette::Result;
+
+/// Enables the given disabled module, so that it's considered again when
+/// resolving modules.
+pub fn module_enable(name: &str, flags: &StandardOptions) -> Result<(), SysexitsError> {
+    set_enabled(name, true, flags)
+}
+
+/// Disables the given module without uninstalling it, so that it's ignored
+/// when resolving modules. Its manifest, or its directory if it has one of
+/// its own, is moved to `modules/disabled/` of the ASIMOV directory it's
+/// installed in.
+pub fn module_disable(name: &str, flags: &StandardOptions) -> Result<(), SysexitsError> {
+    set_enabled(name, false, flags)
+}
+
+fn set_enabled(name: &str, enable: bool, flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let (module, enabled) = find_installed(name)?;
+    let state = if enable { "enabled" } else { "disabled" };
+
+    if enabled == enable {
+        if flags.verbose > 0 {
+            ceprintln!("<s,g>✓</> The module `{}` is already {}.", name, state);
+        }
+        return Ok(());
+    }
+
+    // Modules stay within the ASIMOV directory they're installed in, which
+    // may be a project-local one:
+    let modules_dir = module
+        .root()
+        .map_or_else(module_dir, |root| root.join("modules"));
+    let target_dir = if enable {
+        modules_dir
+    } else {
+        modules_dir.join("disabled")
+    };
+    // Modules installed in a directory of their own are moved as a whole:
+    let source = module.module_dir().unwrap_or(&module.path);
+    let target = target_dir.join(source.file_name().unwrap());
+
+    std::fs::create_dir_all(&target_dir)
+        .and_then(|_| std::fs::rename(source, &target))
+        .map_err(|e| {
+            ceprintln!("<s,r>error:</> failed to move `{}`: {e}", source.display());
+            SysexitsError::EX_IOERR
+        })?;
+
+    if flags.verbose > 0 {
+        ceprintln!("<s,g>✓</> The module `{}` is now {}.", name, state);
+    }
+
+    Ok(())
+}
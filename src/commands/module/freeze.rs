@@ -0,0 +1,30 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{lock::Lockfile, StandardOptions, SysexitsError};
+use color_print::ceprintln;
+use miette::Result;
+use std::path::Path;
+
+/// Writes a lock file recording the installed modules, their versions, and
+/// the checksums of their manifests and programs.
+pub fn module_freeze(path: &Path, flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let lockfile = Lockfile::current().map_err(|e| {
+        ceprintln!("<s,r>error:</> failed to record the installed modules: {e}");
+        SysexitsError::EX_UNAVAILABLE
+    })?;
+
+    lockfile.save(path).map_err(|e| {
+        ceprintln!("<s,r>error:</> {e}");
+        SysexitsError::EX_CANTCREAT
+    })?;
+
+    if flags.verbose > 0 {
+        ceprintln!(
+            "<s,g>✓</> Locked {} modules in `{}`.",
+            lockfile.modules.len(),
+            path.display()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,349 @@
+// This is free and unencumbered software released into the public domain.
+
+use super::remove_module;
+use crate::{
+    config::Config,
+    registry::Registry,
+    shared::{find_module, module_dir, read_manifest, InstalledModule},
+    verify::{self, verify_file, VerifyError},
+    StandardOptions, SysexitsError,
+};
+use asimov_env::paths::asimov_root;
+use color_print::ceprintln;
+use miette::Result;
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+use temp_dir::TempDir;
+
+/// Installs the given module, which is either a local directory or archive
+/// (see [`module_install_path`]) or the name of a module in the registry
+/// index, optionally followed by `@` and a version (e.g., `example@1.0.0`).
+pub fn module_install(
+    module: &str,
+    force: bool,
+    registry: Option<&str>,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let path = Path::new(module);
+    if path.exists() {
+        return module_install_path(path, force, flags);
+    }
+
+    let (name, version) = match module.split_once('@') {
+        Some((name, version)) => (name, Some(version)),
+        None => (module, None),
+    };
+
+    let registry = load_registry(registry)?;
+    let Some(entry) = registry.find(name, version) else {
+        match version {
+            Some(version) => ceprintln!(
+                "<s,r>error:</> no module `{}` version {} in the registry",
+                name,
+                version
+            ),
+            None => ceprintln!("<s,r>error:</> no module `{}` in the registry", name),
+        }
+        return Err(SysexitsError::EX_UNAVAILABLE);
+    };
+
+    if flags.verbose > 1 {
+        ceprintln!(
+            "<s,c>»</> Downloading `{}` version {}...",
+            entry.name,
+            entry.version
+        );
+    }
+
+    let dir = TempDir::new().map_err(|e| {
+        ceprintln!("<s,r>error:</> failed to create a temporary directory: {e}");
+        SysexitsError::EX_CANTCREAT
+    })?;
+    let archive = registry.download(entry, dir.path()).map_err(|e| {
+        ceprintln!("<s,r>error:</> {e}");
+        SysexitsError::EX_UNAVAILABLE
+    })?;
+
+    module_install_path(&archive, force, flags)
+}
+
+/// Loads the registry index at the given location or the configured one,
+/// or prints an error.
+pub(crate) fn load_registry(location: Option<&str>) -> Result<Registry, SysexitsError> {
+    Registry::location(location)
+        .and_then(|location| Registry::load(&location))
+        .map_err(|e| {
+            ceprintln!("<s,r>error:</> {e}");
+            SysexitsError::EX_UNAVAILABLE
+        })
+}
+
+/// Installs the module at the given path, which is either a directory or a
+/// `.tar`, `.tar.gz` or `.tgz` archive containing the module's manifest
+/// (e.g., `module.yaml`) and the programs it declares (at the top level or
+/// in `bin/` or `libexec/`).
+///
+/// The manifest is copied to the module manifest directory and the programs
+/// to the `libexec` directory. An existing install of the module, or other
+/// existing files, are only replaced if `force` is `true`. The module is then
+/// reinstalled where it was installed, e.g., in a project-local directory.
+pub fn module_install_path(
+    path: &Path,
+    force: bool,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let extracted;
+    let source = if path.is_dir() {
+        path.to_path_buf()
+    } else {
+        extracted = extract(path).map_err(|e| {
+            ceprintln!("<s,r>error:</> failed to extract `{}`: {e}", path.display());
+            SysexitsError::EX_DATAERR
+        })?;
+        package_root(extracted.path())
+    };
+
+    let manifest_path = find_manifest(&source).ok_or_else(|| {
+        ceprintln!(
+            "<s,r>error:</> no module manifest found in `{}`",
+            path.display()
+        );
+        SysexitsError::EX_DATAERR
+    })?;
+    let module = read_manifest(&manifest_path).map_err(|e| {
+        ceprintln!("<s,r>error:</> {e}");
+        SysexitsError::EX_DATAERR
+    })?;
+    let name = &module.manifest.name;
+    if !is_valid_name(name) {
+        ceprintln!("<s,r>error:</> invalid module name: `{}`", name);
+        return Err(SysexitsError::EX_DATAERR);
+    }
+
+    let trusted_keys = Config::get()
+        .map(|config| config.trusted_keys.clone())
+        .map_err(|e| {
+            ceprintln!("<s,r>error:</> {e}");
+            SysexitsError::EX_CONFIG
+        })?;
+
+    let existing = find_module(name)
+        .map_err(|e| {
+            ceprintln!("<s,r>error:</> failed to read module manifests: {e}");
+            SysexitsError::EX_UNAVAILABLE
+        })?
+        .map(|(existing, _)| existing);
+    if let Some(existing) = existing.as_ref().filter(|_| !force) {
+        ceprintln!(
+            "<s,r>error:</> the module `{}` is already installed at `{}`",
+            name,
+            existing.path.display()
+        );
+        ceprintln!("<s,y>hint:</> use `--force` to replace it");
+        return Err(SysexitsError::EX_CANTCREAT);
+    }
+
+    // The manifest is always installed as `<name>.yaml`, which is where
+    // `ModuleManifest::read_manifest` looks for it, replacing any directory
+    // of its own the module had:
+    let manifest_dir = existing
+        .as_ref()
+        .and_then(|existing| existing.module_dir().unwrap_or(&existing.path).parent())
+        .map_or_else(module_dir, Path::to_path_buf);
+    let libexec = existing
+        .as_ref()
+        .and_then(InstalledModule::root)
+        .map_or_else(
+            || asimov_root().join("libexec"),
+            |root| root.join("libexec"),
+        );
+
+    // Determine and check all the files to install before copying any:
+    let mut files = vec![(
+        manifest_path.clone(),
+        manifest_dir.join(format!("{}.yaml", name)),
+    )];
+    for program in &module.manifest.provides.programs {
+        if !is_valid_name(program) {
+            ceprintln!("<s,r>error:</> invalid program name: `{}`", program);
+            return Err(SysexitsError::EX_DATAERR);
+        }
+        let Some(program_path) = find_program(&source, program) else {
+            ceprintln!(
+                "<s,r>error:</> the module declares the program `{}`, but it's missing",
+                program
+            );
+            return Err(SysexitsError::EX_DATAERR);
+        };
+
+        // Verify the program against its recorded checksum and signature:
+        let checksum = module.extensions.checksums.get(program);
+        match verify_file(&program_path, checksum.map(String::as_str), &trusted_keys) {
+            Ok(false) if verify::is_required() => {
+                ceprintln!(
+                    "<s,r>error:</> {}",
+                    VerifyError::Unverifiable(program_path.clone())
+                );
+                return Err(SysexitsError::EX_NOPERM);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                ceprintln!("<s,r>error:</> {e}");
+                return Err(SysexitsError::EX_NOPERM);
+            }
+        }
+
+        let signature_path = verify::signature_path(&program_path);
+        if signature_path.is_file() {
+            let filename = signature_path.file_name().unwrap().to_owned();
+            files.push((signature_path, libexec.join(filename)));
+        }
+        let filename = program_path.file_name().unwrap().to_owned();
+        files.push((program_path, libexec.join(filename)));
+    }
+
+    let conflicts = files
+        .iter()
+        .filter(|(_, target)| target.exists())
+        .collect::<Vec<_>>();
+    if !conflicts.is_empty() && !force {
+        for (_, target) in conflicts {
+            ceprintln!("<s,r>error:</> `{}` already exists", target.display());
+        }
+        ceprintln!("<s,y>hint:</> use `--force` to overwrite existing files");
+        return Err(SysexitsError::EX_CANTCREAT);
+    }
+
+    if let Some(existing) = &existing {
+        if flags.verbose > 1 {
+            ceprintln!(
+                "<s,c>»</> Removing the installed module at `{}`...",
+                existing.path.display()
+            );
+        }
+        remove_module(existing, flags)?;
+    }
+
+    for (source, target) in &files {
+        if flags.verbose > 1 {
+            ceprintln!("<s,c>»</> Installing `{}`...", target.display());
+        }
+        install_file(source, target).map_err(|e| {
+            ceprintln!(
+                "<s,r>error:</> failed to install `{}`: {e}",
+                target.display()
+            );
+            SysexitsError::EX_CANTCREAT
+        })?;
+    }
+
+    if flags.verbose > 0 {
+        ceprintln!("<s,g>✓</> Installed the module `{}`.", name);
+    }
+
+    Ok(())
+}
+
+/// Checks whether the given name is usable as a file name.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Extracts the given archive into a temporary directory.
+fn extract(path: &Path) -> std::io::Result<TempDir> {
+    let dir = TempDir::new()?;
+    let file = File::open(path)?;
+
+    let filename = path.file_name().unwrap_or_default().to_string_lossy();
+    if filename.ends_with(".tar.gz") || filename.ends_with(".tgz") {
+        tar::Archive::new(flate2::read::GzDecoder::new(file)).unpack(dir.path())?;
+    } else if filename.ends_with(".tar") {
+        tar::Archive::new(file).unpack(dir.path())?;
+    } else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "unsupported archive format (expected .tar, .tar.gz or .tgz)",
+        ));
+    }
+
+    Ok(dir)
+}
+
+/// Returns the directory containing the package, descending into the single
+/// top-level directory that archives commonly have.
+fn package_root(dir: &Path) -> PathBuf {
+    let entries = std::fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|entry| entry.ok()).collect::<Vec<_>>())
+        .unwrap_or_default();
+    match entries.as_slice() {
+        [entry] if entry.path().is_dir() => entry.path(),
+        _ => dir.to_path_buf(),
+    }
+}
+
+/// Finds the module manifest in the given directory.
+fn find_manifest(dir: &Path) -> Option<PathBuf> {
+    for name in ["module.yaml", "module.yml"] {
+        let path = dir.join(name);
+        if path.is_file() {
+            return Some(path);
+        }
+    }
+
+    let mut manifests = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext == "yaml" || ext == "yml")
+        });
+    let manifest = manifests.next()?;
+    // Refuse to guess between several manifests:
+    manifests.next().is_none().then_some(manifest)
+}
+
+/// Finds the given program in the given directory.
+fn find_program(dir: &Path, program: &str) -> Option<PathBuf> {
+    let filenames = if cfg!(windows) {
+        vec![format!("{}.exe", program), program.to_string()]
+    } else {
+        vec![program.to_string()]
+    };
+    ["", "bin", "libexec"]
+        .iter()
+        .flat_map(|subdir| {
+            filenames
+                .iter()
+                .map(move |name| dir.join(subdir).join(name))
+        })
+        .find(|path| path.is_file())
+}
+
+/// Copies the given file, making it executable if it's a program.
+fn install_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(source, target)?;
+
+    #[cfg(unix)]
+    if target
+        .parent()
+        .is_some_and(|parent| parent.ends_with("libexec"))
+        && target.extension().is_none_or(|ext| ext != "minisig")
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(target, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(())
+}
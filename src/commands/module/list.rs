@@ -0,0 +1,57 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    shared::{project_root, read_disabled_manifests, read_manifests, InstalledModule},
+    StandardOptions, SysexitsError,
+};
+use color_print::{ceprintln, cprintln};
+use miette::Result;
+
+/// Lists the installed modules and the programs they provide, flagging the
+/// modules that are project-local or incompatible with this program.
+pub fn module_list(flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let read = |modules: miette::Result<_>| {
+        modules.map_err(|e| {
+            ceprintln!("<s,r>error:</> failed to read module manifests: {e}");
+            SysexitsError::EX_UNAVAILABLE
+        })
+    };
+    let enabled = read(read_manifests())?;
+    let disabled = read(read_disabled_manifests())?;
+
+    let mut modules = enabled
+        .iter()
+        .map(|module| (module, true))
+        .chain(disabled.iter().map(|module| (module, false)))
+        .collect::<Vec<_>>();
+    modules.sort_by(|(a, _), (b, _)| a.manifest.name.cmp(&b.manifest.name));
+
+    for (module, enabled) in modules {
+        let version = module.extensions.version.as_deref().unwrap_or_default();
+        let scope = match (enabled, is_project_local(module)) {
+            (false, _) => " (disabled)",
+            (true, true) => " (project)",
+            (true, false) => "",
+        };
+        cprintln!(
+            "<s>{}</> <dim>{}{}</>",
+            module.manifest.name,
+            version,
+            scope
+        );
+        if let Err(reason) = module.check_compatibility() {
+            cprintln!("\t<y>incompatible:</> {}", reason);
+        }
+        if flags.verbose > 0 {
+            for program in &module.manifest.provides.programs {
+                cprintln!("\t<dim>$</> {}", program);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn is_project_local(module: &InstalledModule) -> bool {
+    project_root().is_some_and(|root| module.path.starts_with(root))
+}
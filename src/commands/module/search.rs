@@ -0,0 +1,39 @@
+// This is free and unencumbered software released into the public domain.
+
+use super::load_registry;
+use crate::{StandardOptions, SysexitsError};
+use color_print::cprintln;
+use miette::Result;
+
+/// Lists the modules in the registry index whose name or summary contains
+/// the given term, showing the latest version of each.
+pub fn module_search(
+    term: &str,
+    registry: Option<&str>,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let registry = load_registry(registry)?;
+
+    let mut names = registry
+        .search(term)
+        .into_iter()
+        .map(|entry| entry.name.as_str())
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+
+    for name in names {
+        let Some(entry) = registry.find(name, None) else {
+            continue;
+        };
+        cprintln!("<s>{}</> <dim>{}</>", entry.name, entry.version);
+        if !entry.summary.is_empty() {
+            println!("\t{}", entry.summary);
+        }
+        if flags.verbose > 0 {
+            cprintln!("\t<dim>{}</>", entry.url);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+// This is free and unencumbered software released into the public domain.
+
+use super::module_install;
+use crate::{
+    lock::{Drift, Lockfile},
+    StandardOptions, SysexitsError,
+};
+use color_print::ceprintln;
+use miette::Result;
+use std::{collections::BTreeSet, path::Path};
+
+/// Verifies the installed modules against the given lock file. Unless
+/// `check` is `true`, locked modules that are missing or differ are then
+/// (re)installed from the registry index in their locked versions.
+/// Installed modules that aren't locked are only reported.
+pub fn module_sync(
+    path: &Path,
+    check: bool,
+    registry: Option<&str>,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let lockfile = Lockfile::load(path).map_err(|e| {
+        ceprintln!("<s,r>error:</> {e}");
+        SysexitsError::EX_NOINPUT
+    })?;
+
+    let mut drift = diff(&lockfile)?;
+    if !check {
+        let names = drift
+            .iter()
+            .filter(|drift| !matches!(drift, Drift::Unlocked(_)))
+            .map(Drift::module)
+            .collect::<BTreeSet<_>>();
+
+        for name in names {
+            let locked = lockfile.get(name).unwrap();
+            let Some(version) = &locked.version else {
+                ceprintln!(
+                    "<s,r>error:</> can't restore the module `{}` without a locked version",
+                    name
+                );
+                return Err(SysexitsError::EX_DATAERR);
+            };
+            if flags.verbose > 1 {
+                ceprintln!("<s,c>»</> Restoring `{}` version {}...", name, version);
+            }
+            module_install(&format!("{}@{}", name, version), true, registry, flags)?;
+        }
+
+        drift = diff(&lockfile)?;
+    }
+
+    for drift in &drift {
+        match drift {
+            Drift::Unlocked(_) => ceprintln!("<s,y>warning:</> {}", drift),
+            _ => ceprintln!("<s,r>error:</> {}", drift),
+        }
+    }
+    if drift
+        .iter()
+        .any(|drift| !matches!(drift, Drift::Unlocked(_)))
+    {
+        return Err(SysexitsError::EX_DATAERR);
+    }
+
+    if flags.verbose > 0 {
+        ceprintln!(
+            "<s,g>✓</> The installed modules match `{}`.",
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn diff(lockfile: &Lockfile) -> Result<Vec<Drift>, SysexitsError> {
+    let installed = Lockfile::current().map_err(|e| {
+        ceprintln!("<s,r>error:</> failed to record the installed modules: {e}");
+        SysexitsError::EX_UNAVAILABLE
+    })?;
+    Ok(lockfile.diff(&installed))
+}
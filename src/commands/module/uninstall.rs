@@ -0,0 +1,82 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    shared::{find_module, InstalledModule},
+    StandardOptions, SysexitsError,
+};
+use asimov_env::paths::asimov_root;
+use color_print::ceprintln;
+use miette::Result;
+
+/// Uninstalls the module with the given name, removing its manifest (or its
+/// directory, if it has one of its own) and the programs it declares, along
+/// with their signatures.
+pub fn module_uninstall(name: &str, flags: &StandardOptions) -> Result<(), SysexitsError> {
+    let (module, _) = find_installed(name)?;
+    remove_module(&module, flags)?;
+
+    if flags.verbose > 0 {
+        ceprintln!("<s,g>✓</> Uninstalled the module `{}`.", name);
+    }
+
+    Ok(())
+}
+
+/// Removes the files of the given installed module, or prints an error.
+pub(crate) fn remove_module(
+    module: &InstalledModule,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let libexec = module
+        .root()
+        .map(|root| root.join("libexec"))
+        .unwrap_or_else(|| asimov_root().join("libexec"));
+    for program in &module.manifest.provides.programs {
+        for filename in [
+            program.clone(),
+            format!("{}.exe", program),
+            format!("{}.minisig", program),
+            format!("{}.exe.minisig", program),
+        ] {
+            let path = libexec.join(filename);
+            if !path.is_file() {
+                continue;
+            }
+            if flags.verbose > 1 {
+                ceprintln!("<s,c>»</> Removing `{}`...", path.display());
+            }
+            std::fs::remove_file(&path).map_err(|e| {
+                ceprintln!("<s,r>error:</> failed to remove `{}`: {e}", path.display());
+                SysexitsError::EX_IOERR
+            })?;
+        }
+    }
+
+    // Modules installed in a directory of their own are removed as a whole:
+    let removed = match module.module_dir() {
+        Some(dir) => std::fs::remove_dir_all(dir),
+        None => std::fs::remove_file(&module.path),
+    };
+    removed.map_err(|e| {
+        ceprintln!(
+            "<s,r>error:</> failed to remove `{}`: {e}",
+            module.path.display()
+        );
+        SysexitsError::EX_IOERR
+    })
+}
+
+/// Finds the installed module with the given name or prints an error.
+pub(crate) fn find_installed(name: &str) -> Result<(InstalledModule, bool), SysexitsError> {
+    match find_module(name) {
+        Ok(Some(found)) => Ok(found),
+        Ok(None) => {
+            ceprintln!("<s,r>error:</> no such module installed: `{}`", name);
+            Err(SysexitsError::EX_UNAVAILABLE)
+        }
+        Err(e) => {
+            ceprintln!("<s,r>error:</> failed to read module manifests: {e}");
+            Err(SysexitsError::EX_UNAVAILABLE)
+        }
+    }
+}
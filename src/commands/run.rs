@@ -0,0 +1,296 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{
+    commands::External,
+    journal::Journal,
+    shared::{
+        build_resolver, module_args, normalize_input, program_kinds, read_urls, InstalledModule,
+    },
+    throttle::{self, Permit, RateLimits},
+    StandardOptions, SysexitsError,
+};
+use color_print::ceprintln;
+use miette::Result;
+use std::{path::PathBuf, sync::Once, time::Duration};
+
+/// Options for running module programs over a batch of URLs.
+#[derive(Clone, Debug)]
+pub struct BatchOptions {
+    /// How many times to re-run a module program that failed transiently,
+    /// i.e., with an exit code its module declares retriable.
+    pub retries: u32,
+
+    /// The delay before the first retry, which doubles for each further one.
+    pub retry_backoff: Duration,
+
+    /// The journal to record the outcome for each URL in.
+    pub journal: Option<PathBuf>,
+
+    /// Whether to skip the URLs the journal records as processed
+    /// successfully.
+    pub resume: bool,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            retry_backoff: Duration::from_secs(1),
+            journal: None,
+            resume: false,
+        }
+    }
+}
+
+/// The verb describing what a kind of module program does, for messages.
+pub(crate) struct Verb<'a> {
+    /// E.g., "fetch".
+    pub base: &'a str,
+    /// E.g., "Fetching".
+    pub gerund: &'a str,
+    /// E.g., "Fetched".
+    pub past: &'a str,
+}
+
+/// Runs the module program of the given kind (e.g., `fetcher` for
+/// `*-fetcher` programs) resolved for each URL. Any kind provided by an
+/// installed module is accepted.
+pub fn run(
+    kind: &str,
+    urls: &[String],
+    args: &[String],
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let kinds = program_kinds().map_err(|e| {
+        ceprintln!("<s,r>error:</> failed to read module manifests: {e}");
+        SysexitsError::EX_UNAVAILABLE
+    })?;
+    if !kinds.contains(kind) {
+        ceprintln!(
+            "<s,r>error:</> no installed module provides `{}` programs",
+            kind
+        );
+        if !kinds.is_empty() {
+            let kinds = kinds.into_iter().collect::<Vec<_>>().join(", ");
+            ceprintln!("<s,y>hint:</> the available kinds are: {}", kinds);
+        }
+        return Err(SysexitsError::EX_USAGE);
+    }
+
+    let verb = Verb {
+        base: &format!("run a {kind} on"),
+        gerund: &format!("Running {kind} on"),
+        past: &format!("Ran {kind} on"),
+    };
+    run_programs(kind, &verb, urls, args, &BatchOptions::default(), flags)
+}
+
+/// Runs the module program of the given kind resolved for each URL, passing
+/// on the standard options (as declared by the module's manifest) and `args`.
+/// A URL of `-` stands for the URLs read from stdin (see [`read_urls`]).
+///
+/// The programs are throttled per host (see [`crate::throttle`]), and are
+/// retried and journaled as requested by `batch`.
+pub(crate) fn run_programs(
+    kind: &str,
+    verb: &Verb,
+    urls: &[String],
+    args: &[String],
+    batch: &BatchOptions,
+    flags: &StandardOptions,
+) -> Result<(), SysexitsError> {
+    let modules = build_resolver(kind).map_err(|e| {
+        ceprintln!("<s,r>error:</> failed to build a resolver: {e}");
+        SysexitsError::EX_UNAVAILABLE
+    })?;
+
+    let mut journal = open_journal(batch)?;
+
+    for url in &read_urls(urls)? {
+        let url = &normalize_input(url)?;
+
+        if is_completed(&journal, url, verb, flags) {
+            continue;
+        }
+
+        if flags.verbose > 1 {
+            ceprintln!("<s,c>»</> {} `{}`...", verb.gerund, url);
+        }
+
+        match modules.resolve(url)? {
+            Some(module) => {
+                let subcommand = format!("{}-{}", module, kind);
+
+                let cmd = External {
+                    is_debug: flags.debug,
+                    pipe_output: false,
+                    verbosity: flags.verbose,
+                    color: flags.color,
+                    module: modules.get(&module),
+                    ..Default::default()
+                };
+
+                let args = module_args(&modules, &module, args, url, flags);
+                let is_retriable = |code| {
+                    modules
+                        .get(&module)
+                        .is_some_and(|module| module.extensions.is_retriable(code))
+                };
+                let limits = rate_limits(modules.get(&module))?;
+                let code = with_retries(batch, url, is_retriable, || {
+                    let _permit = throttle_host(url, &limits);
+                    cmd.execute(&subcommand, &args).map(|result| result.code)
+                })
+                .unwrap_or_else(|code| code);
+                record(&mut journal, url, &module, code)?;
+                if code.is_failure() {
+                    return Err(code);
+                }
+            }
+            None => {
+                ceprintln!(
+                    "<s,r>error:</> failed to find a module to {} the URL: `{}`",
+                    verb.base,
+                    url
+                );
+                return Err(SysexitsError::EX_SOFTWARE);
+            }
+        }
+
+        if flags.verbose > 0 {
+            ceprintln!("<s,g>✓</> {} `{}`.", verb.past, url);
+        }
+    }
+
+    Ok(())
+}
+
+/// Makes an attempt at processing the given URL, making up to
+/// `batch.retries` further attempts for as long as the attempts fail with
+/// retriable exit codes. Returns the exit code of the last attempt.
+pub fn with_retries(
+    batch: &BatchOptions,
+    url: &str,
+    is_retriable: impl Fn(SysexitsError) -> bool,
+    mut attempt: impl FnMut() -> Result<SysexitsError, SysexitsError>,
+) -> Result<SysexitsError, SysexitsError> {
+    let mut delay = batch.retry_backoff;
+    for retry in 1..=batch.retries {
+        let code = attempt()?;
+        if !code.is_failure() || !is_retriable(code) {
+            return Ok(code);
+        }
+        ceprintln!(
+            "<s,y>warning:</> attempt {} of {} for `{}` failed with {:?}, retrying in {:?}...",
+            retry,
+            batch.retries + 1,
+            url,
+            code,
+            delay
+        );
+        std::thread::sleep(delay);
+        delay = delay.saturating_mul(2);
+    }
+    attempt()
+}
+
+/// Opens the journal requested by `batch`, if any.
+pub(crate) fn open_journal(batch: &BatchOptions) -> Result<Option<Journal>, SysexitsError> {
+    let Some(path) = &batch.journal else {
+        return Ok(None);
+    };
+    Journal::open(path, batch.resume).map(Some).map_err(|e| {
+        ceprintln!(
+            "<s,r>error:</> failed to open the journal `{}`: {e}",
+            path.display()
+        );
+        SysexitsError::EX_CANTCREAT
+    })
+}
+
+/// Checks whether the journal, if any, records the given URL as processed
+/// successfully, in which case it's to be skipped.
+pub(crate) fn is_completed(
+    journal: &Option<Journal>,
+    url: &str,
+    verb: &Verb,
+    flags: &StandardOptions,
+) -> bool {
+    let completed = journal
+        .as_ref()
+        .is_some_and(|journal| journal.is_completed(url));
+    if completed && flags.verbose > 1 {
+        ceprintln!(
+            "<s,c>»</> Skipping `{}`, which was {} before.",
+            url,
+            verb.past.to_lowercase()
+        );
+    }
+    completed
+}
+
+/// Records the outcome of processing the given URL with the given module in
+/// the journal, if any.
+pub(crate) fn record(
+    journal: &mut Option<Journal>,
+    url: &str,
+    module: &str,
+    code: SysexitsError,
+) -> Result<(), SysexitsError> {
+    let Some(journal) = journal else {
+        return Ok(());
+    };
+    journal.record(url, module, code.as_i32()).map_err(|e| {
+        ceprintln!("<s,r>error:</> failed to write to the journal: {e}");
+        SysexitsError::EX_IOERR
+    })
+}
+
+/// Returns the per-host limits on running programs of the given modules
+/// (see [`crate::throttle`]).
+pub(crate) fn rate_limits<'a>(
+    modules: impl IntoIterator<Item = &'a InstalledModule>,
+) -> Result<RateLimits, SysexitsError> {
+    throttle::limits_for(modules).map_err(|e| {
+        ceprintln!("<s,r>error:</> failed to determine rate limits: {e}");
+        SysexitsError::EX_CONFIG
+    })
+}
+
+/// Waits until the per-host limits allow processing the given URL, returning
+/// a permit to be held while processing it. Throttling is merely a courtesy,
+/// so if it can't be coordinated across invocations, it's done in this one.
+pub(crate) fn throttle_host(url: &str, limits: &RateLimits) -> Permit {
+    throttle::acquire(url, limits).unwrap_or_else(|e| {
+        static WARNED: Once = Once::new();
+        WARNED.call_once(|| {
+            ceprintln!(
+                "<s,y>warning:</> failed to throttle `{}` across invocations, so only throttling it in this one: {e}",
+                url
+            );
+        });
+        throttle::acquire_local(url, limits)
+    })
+}
+
+/// Parses a duration given in seconds, optionally with a unit of `ms`, `s`,
+/// `m`, or `h`, e.g., `500ms` or `1.5s`.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let (number, unit) = input.split_at(
+        input
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(input.len()),
+    );
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration: `{}`", input))?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        unit => return Err(format!("invalid duration unit: `{}`", unit)),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("invalid duration: `{}`", input))
+}
@@ -0,0 +1,65 @@
+// This is free and unencumbered software released into the public domain.
+
+use crate::{limits::Limits, throttle::RateLimits};
+use asimov_env::paths::asimov_root;
+use miette::{miette, IntoDiagnostic};
+use serde::Deserialize;
+use std::{path::PathBuf, sync::OnceLock};
+
+static CONFIG: OnceLock<Result<Config, String>> = OnceLock::new();
+
+/// The configuration read from `config.yaml` in the ASIMOV root directory.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The path or URL of the module registry index.
+    pub registry: Option<String>,
+
+    /// Whether module programs must be verified before they are run.
+    pub verify: bool,
+
+    /// Whether module programs are run in the sandbox.
+    pub sandbox: bool,
+
+    /// The resource limits for module programs.
+    pub limits: Limits,
+
+    /// The per-host limits on running module programs.
+    pub rate_limits: RateLimits,
+
+    /// The minisign public keys (in base64) trusted to sign module programs.
+    pub trusted_keys: Vec<String>,
+
+    /// The project directories whose `.asimov` directories may provide
+    /// modules (see [`crate::shared::project_root`]).
+    pub trusted_projects: Vec<PathBuf>,
+}
+
+impl Config {
+    /// Returns the path of the configuration file.
+    pub fn path() -> PathBuf {
+        asimov_root().join("config.yaml")
+    }
+
+    /// Loads the configuration, which defaults to empty if there's no
+    /// configuration file.
+    pub fn load() -> miette::Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = std::fs::File::open(&path).into_diagnostic()?;
+        serde_yml::from_reader(file)
+            .map_err(|e| miette!("Invalid configuration at `{}`: {}", path.display(), e))
+    }
+
+    /// Returns the configuration, which is loaded once per invocation, when
+    /// first needed. Commands that don't need it thus aren't affected by an
+    /// invalid configuration file.
+    pub fn get() -> miette::Result<&'static Self> {
+        CONFIG
+            .get_or_init(|| Self::load().map_err(|e| e.to_string()))
+            .as_ref()
+            .map_err(|e| miette!("{}", e))
+    }
+}
@@ -3,10 +3,16 @@
 /// The set of features that are enabled in this build of the program.
 #[allow(unused)]
 pub static FEATURES: &[&str] = &[
+    #[cfg(feature = "catalog")]
+    "catalog",
     #[cfg(feature = "fetch")]
     "fetch",
     #[cfg(feature = "import")]
     "import",
+    #[cfg(feature = "index")]
+    "index",
+    #[cfg(feature = "module")]
+    "module",
     #[cfg(feature = "unstable")]
     "unstable",
 ];
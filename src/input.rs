@@ -0,0 +1,243 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Normalization of the inputs given to `fetch`, `import` and the like.
+
+use miette::{Diagnostic, SourceSpan};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use url::Url;
+
+#[derive(Debug, Diagnostic, Error)]
+pub enum InputError {
+    #[error("no such file or directory: `{0}`")]
+    #[diagnostic(code(asimov::input::file_not_found))]
+    FileNotFound(String),
+
+    #[error("invalid file path: `{0}`")]
+    #[diagnostic(code(asimov::input::invalid_path))]
+    InvalidPath(String),
+
+    #[error("invalid URL: `{input}`")]
+    #[diagnostic(
+        code(asimov::input::invalid_url),
+        help("expected a URL such as `https://example.com/path`, or a file path")
+    )]
+    InvalidUrl {
+        #[source_code]
+        input: String,
+        #[label("{reason}")]
+        span: SourceSpan,
+        reason: String,
+    },
+}
+
+/// Normalizes the given input to a URL, so that equivalent inputs resolve
+/// identically:
+///
+/// - Local file paths (e.g., `./data.html`) become canonical `file://` URLs.
+/// - A missing scheme defaults to `https`, so `example.com/x` is taken to
+///   mean `https://example.com/x`.
+/// - Hosts are lowercased and converted to ASCII (IDNA), default ports are
+///   dropped, an empty path becomes `/`, and percent-encoding is normalized.
+pub fn normalize(input: &str) -> Result<String, InputError> {
+    if let Some(path) = local_path(input) {
+        return file_url(&path, input);
+    }
+
+    let input = input.trim();
+    let url = if has_scheme(input) {
+        Url::parse(input)
+    } else {
+        Url::parse(&format!("https://{input}"))
+    };
+    let mut url = url.map_err(|error| InputError::InvalidUrl {
+        input: input.to_string(),
+        span: error_span(input, &error),
+        reason: error.to_string(),
+    })?;
+
+    let path = normalize_percent_encoding(url.path());
+    url.set_path(&path);
+    if let Some(query) = url.query().map(normalize_percent_encoding) {
+        url.set_query(Some(&query));
+    }
+
+    Ok(url.into())
+}
+
+/// Checks whether the input starts with a URL scheme, as opposed to a host
+/// optionally followed by a port (e.g., `localhost:8080`).
+fn has_scheme(input: &str) -> bool {
+    if input.contains("://") {
+        return true;
+    }
+    let Some((scheme, rest)) = input.split_once(':') else {
+        return false;
+    };
+    let mut chars = scheme.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-')
+        && !rest.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Returns the span of the input the given parse error most likely refers to.
+fn error_span(input: &str, error: &url::ParseError) -> SourceSpan {
+    use url::ParseError::*;
+
+    let authority_start = input.find("://").map(|i| i + 3).unwrap_or(0);
+    let authority_end = input[authority_start..]
+        .find(['/', '?', '#'])
+        .map(|i| authority_start + i)
+        .unwrap_or(input.len());
+    let authority = &input[authority_start..authority_end];
+    let host_start = authority_start + authority.rfind('@').map(|i| i + 1).unwrap_or(0);
+    let port_start = input[host_start..authority_end]
+        .rfind(':')
+        .filter(|_| !authority.ends_with(']'))
+        .map(|i| host_start + i + 1);
+
+    let (start, end) = match error {
+        InvalidPort => (port_start.unwrap_or(host_start), authority_end),
+        EmptyHost
+        | IdnaError
+        | InvalidIpv4Address
+        | InvalidIpv6Address
+        | InvalidDomainCharacter => (
+            host_start,
+            port_start.map(|i| i - 1).unwrap_or(authority_end),
+        ),
+        _ => (0, input.len()),
+    };
+    (start, end - start).into()
+}
+
+/// Normalizes percent-encoded octets: those of unreserved characters are
+/// decoded, and the hexadecimal digits of the others are uppercased.
+fn normalize_percent_encoding(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut result = String::with_capacity(input.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit()
+        {
+            let hex = &input[i + 1..i + 3];
+            let octet = u8::from_str_radix(hex, 16).unwrap();
+            if octet.is_ascii_alphanumeric() || b"-._~".contains(&octet) {
+                result.push(octet as char);
+            } else {
+                result.push('%');
+                result.push_str(&hex.to_ascii_uppercase());
+            }
+            i += 3;
+            continue;
+        }
+        let c = input[i..].chars().next().unwrap();
+        result.push(c);
+        i += c.len_utf8();
+    }
+    result
+}
+
+/// Returns the path given by the input, if it's a local file path.
+fn local_path(input: &str) -> Option<PathBuf> {
+    if input.starts_with("file:") {
+        return None;
+    }
+    let path = Path::new(input);
+    let is_explicit = path.is_absolute()
+        || input.starts_with("./")
+        || input.starts_with("../")
+        || (cfg!(windows) && (input.starts_with(".\\") || input.starts_with("..\\")));
+    (is_explicit || path.exists()).then(|| path.to_path_buf())
+}
+
+/// Returns the canonical `file://` URL for the given path.
+fn file_url(path: &Path, input: &str) -> Result<String, InputError> {
+    let path = path
+        .canonicalize()
+        .map_err(|_| InputError::FileNotFound(input.to_string()))?;
+    Url::from_file_path(&path)
+        .map(String::from)
+        .map_err(|_| InputError::InvalidPath(input.to_string()))
+}
+
+/// Returns the media type of the file at the given `file:` URL, if known.
+pub fn file_media_type(url: &str) -> Option<&'static str> {
+    let path = Url::parse(url).ok()?.to_file_path().ok()?;
+    detect_media_type(&path)
+}
+
+/// Detects the media type of the given file by its extension or, failing
+/// that, by the magic bytes at its beginning.
+pub fn detect_media_type(path: &Path) -> Option<&'static str> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| media_type_by_extension(&ext.to_ascii_lowercase()))
+        .or_else(|| {
+            let mut buffer = Vec::with_capacity(512);
+            std::fs::File::open(path)
+                .and_then(|file| file.take(512).read_to_end(&mut buffer))
+                .ok()?;
+            media_type_by_magic(&buffer)
+        })
+}
+
+fn media_type_by_extension(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        "csv" => "text/csv",
+        "gif" => "image/gif",
+        "gz" => "application/gzip",
+        "htm" | "html" => "text/html",
+        "jpeg" | "jpg" => "image/jpeg",
+        "json" => "application/json",
+        "jsonld" => "application/ld+json",
+        "md" | "markdown" => "text/markdown",
+        "nq" => "application/n-quads",
+        "nt" => "application/n-triples",
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "rdf" => "application/rdf+xml",
+        "svg" => "image/svg+xml",
+        "trig" => "application/trig",
+        "ttl" => "text/turtle",
+        "txt" => "text/plain",
+        "xml" => "application/xml",
+        "yaml" | "yml" => "application/yaml",
+        "zip" => "application/zip",
+        _ => return None,
+    })
+}
+
+fn media_type_by_magic(bytes: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"%PDF-", "application/pdf"),
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+    ];
+    if let Some((_, media_type)) = SIGNATURES.iter().find(|(sig, _)| bytes.starts_with(sig)) {
+        return Some(media_type);
+    }
+
+    let text = String::from_utf8_lossy(bytes);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    let lowercase = text.to_ascii_lowercase();
+    if lowercase.starts_with("<!doctype html") || lowercase.starts_with("<html") {
+        Some("text/html")
+    } else if text.starts_with("<?xml") {
+        Some("application/xml")
+    } else if text.starts_with('{') || text.starts_with('[') {
+        Some("application/json")
+    } else {
+        None
+    }
+}
@@ -0,0 +1,98 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Journals of batch jobs, which record the outcome of processing each URL,
+//! so that a job can be resumed by skipping the URLs already processed
+//! successfully.
+//!
+//! A journal is a file of JSON lines, each recording a URL, the module that
+//! processed it, the exit code, and the time (in seconds since the Unix
+//! epoch). Journals are only ever appended to, and the last line for a URL
+//! decides whether it was processed successfully.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A line of a journal.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct JournalEntry {
+    pub url: String,
+    pub module: String,
+    pub code: i32,
+    pub timestamp: u64,
+}
+
+/// An open journal.
+pub struct Journal {
+    file: File,
+    completed: HashSet<String>,
+}
+
+impl Journal {
+    /// Opens the journal at the given path, creating it if needed. If
+    /// `resume` is `true`, the URLs the journal records as processed
+    /// successfully are considered completed.
+    pub fn open(path: &Path, resume: bool) -> io::Result<Self> {
+        let mut completed = HashSet::new();
+        if resume && path.exists() {
+            for line in BufReader::new(File::open(path)?).lines() {
+                // An interrupted job may have left a partially written line:
+                let Ok(entry) = serde_json::from_str::<JournalEntry>(&line?) else {
+                    continue;
+                };
+                if entry.code == 0 {
+                    completed.insert(entry.url);
+                } else {
+                    completed.remove(&entry.url);
+                }
+            }
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+
+        // Terminate any partially written line, so that it doesn't garble
+        // the next one:
+        if file.metadata()?.len() > 0 {
+            let mut last = [0];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            if last[0] != b'\n' {
+                file.write_all(b"\n")?;
+            }
+        }
+
+        Ok(Self { file, completed })
+    }
+
+    /// Checks whether the given URL was processed successfully before.
+    pub fn is_completed(&self, url: &str) -> bool {
+        self.completed.contains(url)
+    }
+
+    /// Records the outcome of processing the given URL with the given
+    /// module.
+    pub fn record(&mut self, url: &str, module: &str, code: i32) -> io::Result<()> {
+        let entry = JournalEntry {
+            url: url.to_string(),
+            module: module.to_string(),
+            code,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+        // A single write keeps lines whole, even if the job gets killed:
+        self.file.write_all(line.as_bytes())
+    }
+}
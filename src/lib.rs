@@ -2,9 +2,21 @@
 
 #![allow(unused)]
 
+pub mod cache;
 pub mod commands;
+pub mod config;
 pub mod features;
+pub mod input;
+pub mod journal;
+pub mod limits;
+pub mod lock;
+pub mod process;
+#[cfg(feature = "module")]
+pub mod registry;
+pub mod sandbox;
 pub mod shared;
+pub mod throttle;
+pub mod verify;
 
 use clientele::{StandardOptions, SysexitsError};
 
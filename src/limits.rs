@@ -0,0 +1,207 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Resource limits for module programs, which are enforced with
+//! `setrlimit` right before a program is executed (on Unix only).
+//!
+//! Limits can be set for an invocation with the `--max-*` flags, in the
+//! `limits` configuration setting, and for a module in the `limits` key of
+//! its manifest. Where several of these set the same limit, the strictest
+//! one applies.
+
+use crate::{config::Config, shared::InstalledModule};
+use serde::{de::Error as _, Deserialize, Deserializer};
+use std::{
+    process::{Command, ExitStatus},
+    sync::OnceLock,
+};
+use thiserror::Error;
+
+static LIMITS: OnceLock<Limits> = OnceLock::new();
+
+/// Resource limits for module programs.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Limits {
+    /// The maximum size of the program's memory (i.e., its address space),
+    /// in bytes. Sizes can be given with a unit, e.g., `512M`.
+    #[serde(deserialize_with = "deserialize_size")]
+    pub memory: Option<u64>,
+
+    /// The maximum CPU time of the program, in seconds.
+    pub cpu_time: Option<u64>,
+
+    /// The maximum number of files the program may have open.
+    pub open_files: Option<u64>,
+
+    /// The maximum size of any file the program writes, in bytes. Sizes can
+    /// be given with a unit, e.g., `1G`.
+    #[serde(deserialize_with = "deserialize_size")]
+    pub output_size: Option<u64>,
+}
+
+/// A limit a program was killed for exceeding.
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum LimitExceeded {
+    #[error("exceeded its CPU time limit")]
+    CpuTime,
+
+    #[error("exceeded its output size limit")]
+    OutputSize,
+
+    /// Running out of memory isn't signaled as such, but typically makes
+    /// the program abort or crash.
+    #[error("may have exceeded its memory limit")]
+    Memory,
+}
+
+impl Limits {
+    /// Returns the strictest combination of both limits.
+    pub fn min(self, other: Self) -> Self {
+        fn min(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+        Self {
+            memory: min(self.memory, other.memory),
+            cpu_time: min(self.cpu_time, other.cpu_time),
+            open_files: min(self.open_files, other.open_files),
+            output_size: min(self.output_size, other.output_size),
+        }
+    }
+
+    /// Checks whether no limit is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Sets up the given command to run its program with these limits.
+    pub fn apply(&self, command: &mut Command) {
+        #[cfg(unix)]
+        if !self.is_empty() {
+            let limits = *self;
+            unsafe {
+                use std::os::unix::process::CommandExt;
+                command.pre_exec(move || unix::setrlimits(&limits));
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = command;
+    }
+}
+
+/// Sets the limits for this invocation, as given with the `--max-*` flags.
+pub fn set(limits: Limits) {
+    let _ = LIMITS.set(limits);
+}
+
+/// Returns the limits for a program of the given module, combining those
+/// for this invocation, those configured, and those of the module. Plain
+/// subcommands have no module, and so no limits of their own.
+pub fn for_program(module: Option<&InstalledModule>) -> miette::Result<Limits> {
+    let mut limits = LIMITS.get().copied().unwrap_or_default();
+    limits = limits.min(Config::get()?.limits);
+    if let Some(module) = module {
+        limits = limits.min(module.extensions.limits);
+    }
+    Ok(limits)
+}
+
+/// Returns the limit a program run with the given limits was killed for
+/// exceeding, judging by the signal that terminated it, if any.
+pub fn exceeded(status: &ExitStatus, limits: &Limits) -> Option<LimitExceeded> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        match status.signal()? {
+            libc::SIGXCPU => return Some(LimitExceeded::CpuTime),
+            libc::SIGXFSZ => return Some(LimitExceeded::OutputSize),
+            libc::SIGABRT | libc::SIGSEGV if limits.memory.is_some() => {
+                return Some(LimitExceeded::Memory)
+            }
+            _ => {}
+        }
+    }
+    let _ = (status, limits);
+    None
+}
+
+/// Parses a size in bytes, with an optional binary unit, e.g., `512M`.
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let digits = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(digits);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid size: `{}`", input))?;
+    let shift = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 10,
+        "M" | "MB" | "MIB" => 20,
+        "G" | "GB" | "GIB" => 30,
+        "T" | "TB" | "TIB" => 40,
+        _ => return Err(format!("invalid size unit: `{}`", unit)),
+    };
+    number
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("size too large: `{}`", input))
+}
+
+fn deserialize_size<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+
+    match Option::<Size>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Size::Bytes(bytes)) => Ok(Some(bytes)),
+        Some(Size::Text(text)) => parse_size(&text).map(Some).map_err(D::Error::custom),
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::Limits;
+    use std::io;
+
+    /// Sets the given limits on this process. This is async-signal-safe.
+    pub(super) fn setrlimits(limits: &Limits) -> io::Result<()> {
+        let resources = [
+            (libc::RLIMIT_AS, limits.memory, 0),
+            // The soft limit raises SIGXCPU, while the hard limit would
+            // raise SIGKILL, which can't be told apart from other kills:
+            (libc::RLIMIT_CPU, limits.cpu_time, 1),
+            (libc::RLIMIT_NOFILE, limits.open_files, 0),
+            (libc::RLIMIT_FSIZE, limits.output_size, 0),
+        ];
+        for (resource, limit, grace) in resources {
+            let Some(limit) = limit else {
+                continue;
+            };
+            unsafe {
+                let mut current: libc::rlimit = std::mem::zeroed();
+                if libc::getrlimit(resource, &mut current) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                // Limits can't be raised beyond the current hard limit:
+                let limit = limit as libc::rlim_t;
+                let hard = limit.saturating_add(grace).min(current.rlim_max);
+                let new = libc::rlimit {
+                    rlim_cur: limit.min(hard),
+                    rlim_max: hard,
+                };
+                if libc::setrlimit(resource, &new) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+        Ok(())
+    }
+}
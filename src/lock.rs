@@ -0,0 +1,187 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Lock files (`asimov.lock`), which record a set of installed modules so
+//! that it can be reproduced elsewhere.
+
+use crate::{
+    shared::{read_manifests, InstalledModule},
+    verify::sha256_file,
+};
+use miette::{miette, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, path::Path};
+
+/// The default path of the lock file, relative to the current directory.
+pub static LOCK_FILE: &str = "asimov.lock";
+
+/// A set of modules, as recorded in a lock file.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct Lockfile {
+    pub modules: Vec<LockedModule>,
+}
+
+/// A module, as recorded in a lock file.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct LockedModule {
+    pub name: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// The SHA-256 checksum of the module's manifest, in hexadecimal.
+    pub manifest: String,
+
+    /// The SHA-256 checksums of the module's programs, in hexadecimal.
+    pub programs: BTreeMap<String, String>,
+}
+
+/// A difference between a lock file and the installed modules.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Drift {
+    /// A locked module isn't installed.
+    Missing(String),
+
+    /// A locked module is installed in a different version.
+    Version {
+        name: String,
+        locked: Option<String>,
+        installed: Option<String>,
+    },
+
+    /// A locked module's manifest differs.
+    Manifest(String),
+
+    /// A locked module's program differs or is missing.
+    Program { name: String, program: String },
+
+    /// An installed module isn't locked.
+    Unlocked(String),
+}
+
+impl Drift {
+    /// Returns the name of the module concerned.
+    pub fn module(&self) -> &str {
+        match self {
+            Drift::Missing(name)
+            | Drift::Version { name, .. }
+            | Drift::Manifest(name)
+            | Drift::Program { name, .. }
+            | Drift::Unlocked(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Drift::Missing(name) => write!(f, "the module `{}` isn't installed", name),
+            Drift::Version {
+                name,
+                locked,
+                installed,
+            } => write!(
+                f,
+                "the module `{}` is at version {}, but {} is locked",
+                name,
+                installed.as_deref().unwrap_or("(none)"),
+                locked.as_deref().unwrap_or("(none)")
+            ),
+            Drift::Manifest(name) => write!(f, "the manifest of the module `{}` differs", name),
+            Drift::Program { name, program } => write!(
+                f,
+                "the program `{}` of the module `{}` differs or is missing",
+                program, name
+            ),
+            Drift::Unlocked(name) => write!(f, "the module `{}` isn't locked", name),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Records the currently installed (and enabled) modules.
+    pub fn current() -> miette::Result<Self> {
+        let mut modules = read_manifests()?
+            .iter()
+            .map(LockedModule::from_installed)
+            .collect::<miette::Result<Vec<_>>>()?;
+        modules.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(Self { modules })
+    }
+
+    /// Loads the lock file at the given path.
+    pub fn load(path: &Path) -> miette::Result<Self> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| miette!("Failed to open `{}`: {}", path.display(), e))?;
+        serde_yml::from_reader(file)
+            .map_err(|e| miette!("Invalid lock file at `{}`: {}", path.display(), e))
+    }
+
+    /// Saves the lock file at the given path.
+    pub fn save(&self, path: &Path) -> miette::Result<()> {
+        let yaml = serde_yml::to_string(self).into_diagnostic()?;
+        std::fs::write(path, yaml)
+            .map_err(|e| miette!("Failed to write `{}`: {}", path.display(), e))
+    }
+
+    /// Compares this lock file against the given, installed set of modules.
+    pub fn diff(&self, installed: &Lockfile) -> Vec<Drift> {
+        let mut drift = Vec::new();
+
+        for locked in &self.modules {
+            let Some(module) = installed.get(&locked.name) else {
+                drift.push(Drift::Missing(locked.name.clone()));
+                continue;
+            };
+            if module.version != locked.version {
+                drift.push(Drift::Version {
+                    name: locked.name.clone(),
+                    locked: locked.version.clone(),
+                    installed: module.version.clone(),
+                });
+            }
+            if module.manifest != locked.manifest {
+                drift.push(Drift::Manifest(locked.name.clone()));
+            }
+            for (program, checksum) in &locked.programs {
+                if module.programs.get(program) != Some(checksum) {
+                    drift.push(Drift::Program {
+                        name: locked.name.clone(),
+                        program: program.clone(),
+                    });
+                }
+            }
+        }
+
+        for module in &installed.modules {
+            if self.get(&module.name).is_none() {
+                drift.push(Drift::Unlocked(module.name.clone()));
+            }
+        }
+
+        drift
+    }
+
+    /// Returns the locked module with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<&LockedModule> {
+        self.modules.iter().find(|module| module.name == name)
+    }
+}
+
+impl LockedModule {
+    fn from_installed(module: &InstalledModule) -> miette::Result<Self> {
+        let mut programs = BTreeMap::new();
+        for program in &module.manifest.provides.programs {
+            // Programs installed elsewhere (e.g., in `PATH`) aren't locked:
+            if let Some(path) = module.program_path(program) {
+                programs.insert(program.clone(), sha256_file(&path).into_diagnostic()?);
+            }
+        }
+
+        Ok(Self {
+            name: module.manifest.name.clone(),
+            version: module.extensions.version.clone(),
+            manifest: sha256_file(&module.path).into_diagnostic()?,
+            programs,
+        })
+    }
+}
@@ -5,10 +5,11 @@
 
 use asimov_cli::commands::{self, External, Help, HelpCmd};
 use clientele::{
-    crates::clap::{CommandFactory, Parser, Subcommand as ClapSubcommand},
+    crates::clap::{Args, CommandFactory, Parser, Subcommand as ClapSubcommand},
     StandardOptions, SubcommandsProvider,
     SysexitsError::{self, *},
 };
+use std::{path::PathBuf, time::Duration};
 
 /// ASIMOV Command-Line Interface (CLI)
 #[derive(Debug, Parser)]
@@ -24,10 +25,94 @@ struct Options {
     #[clap(short = 'h', long, help = "Print help (see more with '--help')")]
     help: bool,
 
+    #[clap(
+        long,
+        global = true,
+        help = "Refuse to run module programs that can't be verified"
+    )]
+    verify: bool,
+
+    #[clap(
+        long,
+        global = true,
+        help = "Run module programs in a sandbox (Linux only)"
+    )]
+    sandbox: bool,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "SIZE",
+        value_parser = asimov_cli::limits::parse_size,
+        help = "Limit the memory of module programs (e.g., 512M)"
+    )]
+    max_memory: Option<u64>,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "SECONDS",
+        help = "Limit the CPU time of module programs"
+    )]
+    max_cpu_time: Option<u64>,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "COUNT",
+        help = "Limit the number of files module programs may open"
+    )]
+    max_open_files: Option<u64>,
+
+    #[clap(
+        long,
+        global = true,
+        value_name = "SIZE",
+        value_parser = asimov_cli::limits::parse_size,
+        help = "Limit the size of files module programs write (e.g., 1G)"
+    )]
+    max_output_size: Option<u64>,
+
     #[clap(subcommand)]
     command: Option<Command>,
 }
 
+/// The options for running module programs over a batch of URLs.
+#[derive(Debug, Args)]
+struct BatchArgs {
+    /// Re-run a module up to N times when it fails transiently
+    #[clap(long, value_name = "N", default_value_t = 0)]
+    retries: u32,
+
+    /// The delay before the first retry, doubling for each further one
+    #[clap(
+        long,
+        value_name = "DURATION",
+        default_value = "1s",
+        value_parser = commands::parse_duration
+    )]
+    retry_backoff: Duration,
+
+    /// Record the outcome for each URL in a journal file
+    #[clap(long, value_name = "FILE")]
+    journal: Option<PathBuf>,
+
+    /// Skip the URLs the journal records as processed successfully
+    #[clap(long, requires = "journal")]
+    resume: bool,
+}
+
+impl From<&BatchArgs> for commands::BatchOptions {
+    fn from(args: &BatchArgs) -> Self {
+        Self {
+            retries: args.retries,
+            retry_backoff: args.retry_backoff,
+            journal: args.journal.clone(),
+            resume: args.resume,
+        }
+    }
+}
+
 #[derive(Debug, ClapSubcommand)]
 enum Command {
     /// Print help for a subcommand
@@ -36,18 +121,159 @@ enum Command {
         args: Vec<String>,
     },
 
+    /// List the resources behind a URL, utilizing enabled modules
+    #[cfg(feature = "catalog")]
+    Catalog {
+        urls: Vec<String>,
+
+        /// Print the listing as a JSON array
+        #[clap(long)]
+        json: bool,
+
+        /// Extra arguments to pass on to the module
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+
     /// Fetch raw data from a URL, utilizing enabled modules
     #[cfg(feature = "fetch")]
-    Fetch { urls: Vec<String> },
+    Fetch {
+        urls: Vec<String>,
+
+        /// Import the fetched data right away, piping it into an importer
+        #[cfg(feature = "import")]
+        #[clap(long)]
+        import: bool,
+
+        #[clap(flatten)]
+        batch: BatchArgs,
+
+        /// Extra arguments to pass on to the module
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
 
     /// Import knowledge from a URL, utilizing enabled modules
     #[cfg(feature = "import")]
-    Import { urls: Vec<String> },
+    Import {
+        #[clap(conflicts_with = "from_stdin")]
+        urls: Vec<String>,
+
+        /// Import data read from stdin instead of fetching it
+        #[clap(
+            long,
+            requires = "source",
+            conflicts_with_all = ["retries", "retry_backoff", "journal", "resume"]
+        )]
+        from_stdin: bool,
+
+        /// The URL or media type of the data read from stdin
+        #[clap(long = "as", value_name = "URL_OR_MEDIA_TYPE", requires = "from_stdin")]
+        source: Option<String>,
+
+        #[clap(flatten)]
+        batch: BatchArgs,
+
+        /// Extra arguments to pass on to the module
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+
+    /// Index the data at a URL, utilizing enabled modules
+    #[cfg(feature = "index")]
+    Index {
+        urls: Vec<String>,
+
+        /// Extra arguments to pass on to the module
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
+
+    /// Manage installed modules
+    #[cfg(feature = "module")]
+    Module {
+        #[clap(subcommand)]
+        command: ModuleCommand,
+    },
+
+    /// Run module programs of any kind on a URL, utilizing enabled modules
+    Run {
+        /// The kind of module program, e.g., `fetcher` or `importer`
+        kind: String,
+
+        urls: Vec<String>,
+
+        /// Extra arguments to pass on to the module
+        #[clap(last = true)]
+        args: Vec<String>,
+    },
 
     #[clap(external_subcommand)]
     External(Vec<String>),
 }
 
+#[cfg(feature = "module")]
+#[derive(Debug, ClapSubcommand)]
+enum ModuleCommand {
+    /// List installed modules
+    List,
+
+    /// Install a module from a local directory or archive, or the registry
+    Install {
+        /// The module directory, `.tar`, `.tar.gz` or `.tgz` archive, or
+        /// name (optionally followed by `@` and a version)
+        module: String,
+
+        /// Overwrite existing files
+        #[clap(short = 'f', long)]
+        force: bool,
+
+        /// The path or URL of the module registry index
+        #[clap(long)]
+        registry: Option<String>,
+    },
+
+    /// Search the module registry
+    Search {
+        term: String,
+
+        /// The path or URL of the module registry index
+        #[clap(long)]
+        registry: Option<String>,
+    },
+
+    /// Record the installed modules in a lock file
+    Freeze {
+        /// The path of the lock file
+        #[clap(long, default_value = asimov_cli::lock::LOCK_FILE)]
+        lockfile: PathBuf,
+    },
+
+    /// Verify or restore the installed modules against a lock file
+    Sync {
+        /// The path of the lock file
+        #[clap(long, default_value = asimov_cli::lock::LOCK_FILE)]
+        lockfile: PathBuf,
+
+        /// Only verify the installed modules, without restoring any
+        #[clap(long)]
+        check: bool,
+
+        /// The path or URL of the module registry index
+        #[clap(long)]
+        registry: Option<String>,
+    },
+
+    /// Uninstall a module, removing its manifest and programs
+    Uninstall { name: String },
+
+    /// Enable a disabled module
+    Enable { name: String },
+
+    /// Disable a module without uninstalling it
+    Disable { name: String },
+}
+
 pub fn main() -> SysexitsError {
     // Load environment variables from `.env`:
     clientele::dotenv().ok();
@@ -80,6 +306,24 @@ pub fn main() -> SysexitsError {
         std::env::set_var("RUST_BACKTRACE", "1");
     }
 
+    // Require verification of module programs, if requested:
+    if options.verify {
+        asimov_cli::verify::require();
+    }
+
+    // Sandbox module programs, if requested:
+    if options.sandbox {
+        asimov_cli::sandbox::enable();
+    }
+
+    // Limit the resources of module programs, if requested:
+    asimov_cli::limits::set(asimov_cli::limits::Limits {
+        memory: options.max_memory,
+        cpu_time: options.max_cpu_time,
+        open_files: options.max_open_files,
+        output_size: options.max_output_size,
+    });
+
     // Print the help message, if requested:
     if options.help {
         print_help();
@@ -91,6 +335,7 @@ pub fn main() -> SysexitsError {
             if let Some(cmd_name) = args.first() {
                 let cmd = HelpCmd {
                     is_debug: options.flags.debug,
+                    color: options.flags.color,
                 };
 
                 let result = cmd.execute(cmd_name, &args[1..]);
@@ -118,17 +363,87 @@ pub fn main() -> SysexitsError {
                 Ok(EX_OK)
             }
         }
+        #[cfg(feature = "catalog")]
+        Command::Catalog { urls, json, args } => {
+            commands::catalog(urls, args, *json, &options.flags).map(|_| EX_OK)
+        }
         #[cfg(feature = "fetch")]
-        Command::Fetch { urls } => commands::fetch(urls, &options.flags).map(|_| EX_OK),
+        Command::Fetch {
+            urls,
+            #[cfg(feature = "import")]
+            import,
+            batch,
+            args,
+        } => {
+            let batch = batch.into();
+            #[cfg(feature = "import")]
+            let result = if *import {
+                commands::fetch_and_import(urls, args, &batch, &options.flags)
+            } else {
+                commands::fetch(urls, args, &batch, &options.flags)
+            };
+            #[cfg(not(feature = "import"))]
+            let result = commands::fetch(urls, args, &batch, &options.flags);
+            result.map(|_| EX_OK)
+        }
         #[cfg(feature = "import")]
-        Command::Import { urls } => commands::import(urls, &options.flags).map(|_| EX_OK),
+        Command::Import {
+            urls,
+            from_stdin,
+            source,
+            batch,
+            args,
+        } => match source {
+            // Data read from stdin can't be read again, so isn't retried or
+            // journaled (hence the conflicting flags):
+            Some(source) if *from_stdin => {
+                commands::import_stdin(source, args, &options.flags).map(|_| EX_OK)
+            }
+            _ => commands::import(urls, args, &batch.into(), &options.flags).map(|_| EX_OK),
+        },
+        #[cfg(feature = "index")]
+        Command::Index { urls, args } => commands::index(urls, args, &options.flags).map(|_| EX_OK),
+        #[cfg(feature = "module")]
+        Command::Module { command } => match command {
+            ModuleCommand::List => commands::module_list(&options.flags),
+            ModuleCommand::Install {
+                module,
+                force,
+                registry,
+            } => commands::module_install(module, *force, registry.as_deref(), &options.flags),
+            ModuleCommand::Search { term, registry } => {
+                commands::module_search(term, registry.as_deref(), &options.flags)
+            }
+            ModuleCommand::Freeze { lockfile } => commands::module_freeze(lockfile, &options.flags),
+            ModuleCommand::Sync {
+                lockfile,
+                check,
+                registry,
+            } => commands::module_sync(lockfile, *check, registry.as_deref(), &options.flags),
+            ModuleCommand::Uninstall { name } => commands::module_uninstall(name, &options.flags),
+            ModuleCommand::Enable { name } => commands::module_enable(name, &options.flags),
+            ModuleCommand::Disable { name } => commands::module_disable(name, &options.flags),
+        }
+        .map(|_| EX_OK),
+        Command::Run { kind, urls, args } => {
+            commands::run(kind, urls, args, &options.flags).map(|_| EX_OK)
+        }
         Command::External(args) => {
             let cmd = External {
                 is_debug: options.flags.debug,
                 pipe_output: false,
+                verbosity: options.flags.verbose,
+                color: options.flags.color,
+                exec: true,
+                ..Default::default()
             };
 
-            cmd.execute(&args[0], &args[1..]).map(|result| result.code)
+            match cmd.execute(&args[0], &args[1..]) {
+                // Exit with the subcommand's own status, which isn't
+                // necessarily a sysexits value (e.g., 130 after Ctrl-C):
+                Ok(result) => std::process::exit(result.status),
+                Err(error) => Err(error),
+            }
         }
     };
 
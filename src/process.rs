@@ -0,0 +1,234 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Job control for module subprocesses.
+
+use crate::limits::Limits;
+use clientele::SysexitsError::*;
+use std::process::{Child, Command, ExitStatus};
+
+/// A subprocess run as a job of this process.
+///
+/// On Unix, the subprocess gets its own process group, which is made the
+/// foreground process group of the controlling terminal (if we own it) for
+/// as long as the subprocess runs. Meanwhile, `SIGINT`, `SIGTERM` and
+/// `SIGHUP` delivered to this process are forwarded to the subprocess, and
+/// suspending the subprocess (e.g., with Ctrl-Z) suspends this process too.
+///
+/// Several subprocesses (e.g., the stages of a pipeline) can form a single
+/// job by joining the process group of the first one, the job's leader.
+pub(crate) struct Job {
+    pub(crate) child: Child,
+    /// The resource limits the subprocess was spawned with.
+    pub(crate) limits: Limits,
+    #[cfg(unix)]
+    pgid: i32,
+    #[cfg(unix)]
+    foreground: bool,
+    #[cfg(unix)]
+    forwarding: Option<unix::Forwarding>,
+}
+
+impl Job {
+    /// Spawns the given command as a job, or as a member of the job led by
+    /// `leader`. The leader must be waited for last.
+    pub(crate) fn spawn(command: &mut Command, leader: Option<&Job>) -> std::io::Result<Self> {
+        #[cfg(unix)]
+        return unix::spawn(command, leader);
+        #[cfg(not(unix))]
+        {
+            let _ = leader;
+            Ok(Self {
+                child: command.spawn()?,
+                limits: Limits::default(),
+            })
+        }
+    }
+
+    /// Waits for the subprocess to exit.
+    pub(crate) fn wait(mut self) -> std::io::Result<ExitStatus> {
+        #[cfg(unix)]
+        return unix::wait(&mut self);
+        #[cfg(not(unix))]
+        return self.child.wait();
+    }
+}
+
+/// Returns the shell-compatible exit status for the given process status,
+/// i.e., the exit code or 128 plus the number of the terminating signal.
+pub fn exit_status(status: &ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+
+    status.code().unwrap_or(EX_SOFTWARE.as_i32())
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::{Job, Limits};
+    use std::{
+        io::{self, IsTerminal},
+        os::unix::process::{CommandExt, ExitStatusExt},
+        process::{Child, Command, ExitStatus},
+        sync::atomic::{AtomicI32, Ordering},
+    };
+
+    /// The signals forwarded to the process group of the running job.
+    const FORWARDED_SIGNALS: [libc::c_int; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+    /// The process group of the running job, or zero if there is none.
+    static JOB_PGID: AtomicI32 = AtomicI32::new(0);
+
+    extern "C" fn forward_signal(signal: libc::c_int) {
+        let pgid = JOB_PGID.load(Ordering::SeqCst);
+        if pgid > 0 {
+            unsafe { libc::kill(-pgid, signal) };
+        }
+    }
+
+    /// Checks whether our process group is in the foreground of the terminal.
+    fn owns_terminal() -> bool {
+        io::stdin().is_terminal()
+            && unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp() }
+    }
+
+    /// Makes the given process group the foreground process group of the
+    /// terminal. This is async-signal-safe.
+    unsafe fn set_foreground(pgid: libc::pid_t) {
+        unsafe {
+            // Background process groups get SIGTTOU when calling tcsetpgrp():
+            let previous = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+            libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
+            libc::signal(libc::SIGTTOU, previous);
+        }
+    }
+
+    pub(super) fn spawn(command: &mut Command, leader: Option<&Job>) -> io::Result<Job> {
+        if let Some(leader) = leader {
+            // The leader already owns the terminal and forwards signals to
+            // the whole process group:
+            command.process_group(leader.pgid);
+            return Ok(Job {
+                child: command.spawn()?,
+                limits: Limits::default(),
+                pgid: leader.pgid,
+                foreground: leader.foreground,
+                forwarding: None,
+            });
+        }
+
+        let foreground = owns_terminal();
+
+        command.process_group(0);
+        if foreground {
+            // Both the child and the parent hand over the terminal, so that
+            // the child can't read from it before it's in the foreground:
+            unsafe {
+                command.pre_exec(|| {
+                    set_foreground(libc::getpgrp());
+                    Ok(())
+                });
+            }
+        }
+
+        let child = command.spawn()?;
+        let pgid = child.id() as libc::pid_t;
+
+        let forwarding = Forwarding::install(pgid);
+        if foreground {
+            unsafe { set_foreground(pgid) };
+        }
+
+        Ok(Job {
+            child,
+            limits: Limits::default(),
+            pgid,
+            foreground,
+            forwarding: Some(forwarding),
+        })
+    }
+
+    pub(super) fn wait(job: &mut Job) -> io::Result<ExitStatus> {
+        let pid = job.child.id() as libc::pid_t;
+        let pgid = job.pgid;
+
+        let result = loop {
+            let mut status = 0;
+            if unsafe { libc::waitpid(pid, &mut status, libc::WUNTRACED) } < 0 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                break Err(error);
+            }
+
+            if libc::WIFSTOPPED(status) {
+                // The job was suspended, so suspend ourselves as well and
+                // resume the job once we get continued:
+                unsafe {
+                    if job.foreground {
+                        set_foreground(libc::getpgrp());
+                    }
+                    libc::kill(libc::getpid(), libc::SIGSTOP);
+                    if job.foreground {
+                        set_foreground(pgid);
+                    }
+                    libc::kill(-pgid, libc::SIGCONT);
+                }
+                continue;
+            }
+
+            break Ok(ExitStatus::from_raw(status));
+        };
+
+        // Only the leader takes back the terminal, once the job is done:
+        if job.foreground && job.forwarding.is_some() {
+            unsafe { set_foreground(libc::getpgrp()) };
+        }
+
+        result
+    }
+
+    /// Forwards signals to a process group until dropped.
+    pub(super) struct Forwarding {
+        previous: Vec<(libc::c_int, libc::sigaction)>,
+    }
+
+    impl Forwarding {
+        fn install(pgid: libc::pid_t) -> Self {
+            JOB_PGID.store(pgid, Ordering::SeqCst);
+
+            let mut previous = Vec::new();
+            for signal in FORWARDED_SIGNALS {
+                unsafe {
+                    let mut action: libc::sigaction = std::mem::zeroed();
+                    action.sa_sigaction =
+                        forward_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+                    action.sa_flags = libc::SA_RESTART;
+                    libc::sigemptyset(&mut action.sa_mask);
+
+                    let mut old: libc::sigaction = std::mem::zeroed();
+                    if libc::sigaction(signal, &action, &mut old) == 0 {
+                        previous.push((signal, old));
+                    }
+                }
+            }
+
+            Self { previous }
+        }
+    }
+
+    impl Drop for Forwarding {
+        fn drop(&mut self) {
+            for (signal, old) in &self.previous {
+                unsafe { libc::sigaction(*signal, old, std::ptr::null_mut()) };
+            }
+            JOB_PGID.store(0, Ordering::SeqCst);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Module registry indexes, which list the modules available for installation.
+//!
+//! A registry index is a YAML (or JSON) file such as:
+//!
+//! ```yaml
+//! modules:
+//!   - name: example
+//!     version: 1.0.0
+//!     summary: An example module
+//!     url: example-1.0.0.tar.gz
+//!     sha256: 0123456789abcdef...
+//! ```
+//!
+//! Relative download URLs are relative to the location of the index, so a
+//! private mirror is simply a directory (or web server) with an index and
+//! the module archives.
+
+use crate::config::Config;
+use miette::{miette, IntoDiagnostic};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+use url::Url;
+
+/// A module registry index.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Registry {
+    pub modules: Vec<RegistryEntry>,
+
+    /// The location the index was loaded from.
+    #[serde(skip)]
+    pub location: String,
+}
+
+/// A version of a module listed in a registry index.
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegistryEntry {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub summary: String,
+
+    /// The location of the module archive, relative to the index.
+    pub url: String,
+
+    /// The expected SHA-256 checksum of the module archive, in hexadecimal.
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+impl Registry {
+    /// Returns the location of the registry index: the given one, or else
+    /// the `ASIMOV_REGISTRY` environment variable, or else the `registry`
+    /// configuration setting.
+    pub fn location(given: Option<&str>) -> miette::Result<String> {
+        if let Some(location) = given {
+            return Ok(location.to_string());
+        }
+        if let Ok(location) = std::env::var("ASIMOV_REGISTRY") {
+            return Ok(location);
+        }
+        Config::get()?.registry.clone().ok_or_else(|| {
+            miette!(
+                "No module registry configured; use `--registry`, set `ASIMOV_REGISTRY`, or set `registry` in `{}`",
+                Config::path().display()
+            )
+        })
+    }
+
+    /// Loads the registry index at the given path or URL.
+    pub fn load(location: &str) -> miette::Result<Self> {
+        let mut data = Vec::new();
+        open(location)?.read_to_end(&mut data).into_diagnostic()?;
+        let mut registry: Registry = serde_yml::from_slice(&data)
+            .map_err(|e| miette!("Invalid module registry index at `{}`: {}", location, e))?;
+        registry.location = location.to_string();
+        Ok(registry)
+    }
+
+    /// Returns the modules whose name or summary contains the given term.
+    pub fn search(&self, term: &str) -> Vec<&RegistryEntry> {
+        let term = term.to_lowercase();
+        self.modules
+            .iter()
+            .filter(|entry| {
+                entry.name.to_lowercase().contains(&term)
+                    || entry.summary.to_lowercase().contains(&term)
+            })
+            .collect()
+    }
+
+    /// Returns the given version of the named module, or its latest version.
+    pub fn find(&self, name: &str, version: Option<&str>) -> Option<&RegistryEntry> {
+        let mut versions = self.modules.iter().filter(|entry| entry.name == name);
+        match version {
+            Some(version) => versions.find(|entry| entry.version == version),
+            None => versions.max_by(|a, b| {
+                match (
+                    semver::Version::parse(&a.version),
+                    semver::Version::parse(&b.version),
+                ) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    _ => a.version.cmp(&b.version),
+                }
+            }),
+        }
+    }
+
+    /// Downloads the archive of the given module into the given directory,
+    /// verifying its checksum, and returns the path of the archive.
+    pub fn download(&self, entry: &RegistryEntry, dir: &Path) -> miette::Result<PathBuf> {
+        let location = self.resolve(&entry.url);
+        let filename = location
+            .rsplit(['/', '\\'])
+            .next()
+            .filter(|name| !name.is_empty())
+            .unwrap_or("module.tar.gz");
+        let path = dir.join(filename);
+
+        let mut data = Vec::new();
+        open(&location)?.read_to_end(&mut data).into_diagnostic()?;
+
+        if let Some(expected) = &entry.sha256 {
+            let actual = format!("{:x}", Sha256::digest(&data));
+            if !actual.eq_ignore_ascii_case(expected) {
+                return Err(miette!(
+                    "Checksum mismatch for `{}`: expected {}, got {}",
+                    location,
+                    expected,
+                    actual
+                ));
+            }
+        }
+
+        std::fs::write(&path, data).into_diagnostic()?;
+        Ok(path)
+    }
+
+    /// Resolves the given location relative to the location of the index.
+    fn resolve(&self, location: &str) -> String {
+        if is_url(location) || Path::new(location).is_absolute() {
+            return location.to_string();
+        }
+        if is_url(&self.location) {
+            return Url::parse(&self.location)
+                .and_then(|base| base.join(location))
+                .map(String::from)
+                .unwrap_or_else(|_| location.to_string());
+        }
+        Path::new(&self.location)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(location)
+            .display()
+            .to_string()
+    }
+}
+
+fn is_url(location: &str) -> bool {
+    location.starts_with("http://")
+        || location.starts_with("https://")
+        || location.starts_with("file:")
+}
+
+/// Opens the file at the given path or URL for reading.
+fn open(location: &str) -> miette::Result<Box<dyn Read>> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        let response = ureq::get(location)
+            .call()
+            .map_err(|e| miette!("Failed to download `{}`: {}", location, e))?;
+        return Ok(Box::new(response.into_reader()));
+    }
+
+    let path = match Url::parse(location) {
+        Ok(url) if url.scheme() == "file" => url
+            .to_file_path()
+            .map_err(|_| miette!("Invalid file URL: `{}`", location))?,
+        _ => PathBuf::from(location),
+    };
+    let file = std::fs::File::open(&path)
+        .map_err(|e| miette!("Failed to open `{}`: {}", path.display(), e))?;
+    Ok(Box::new(file))
+}
@@ -0,0 +1,323 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Sandboxing of module programs, which is opt-in and only supported on
+//! Linux.
+//!
+//! In the sandbox, a program may read and execute any file, but may only
+//! write beneath the output directory (i.e., the current directory), the
+//! temporary directory, and the paths listed under `capabilities.write` in
+//! its module's manifest, as enforced with Landlock. It may also write to
+//! devices such as `/dev/null` and the terminal. Programs of modules that
+//! declare `capabilities.network: false` are additionally run in a network
+//! namespace of their own, which has no network access.
+
+use crate::{
+    config::Config,
+    shared::{Capabilities, InstalledModule},
+};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use thiserror::Error;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Error)]
+pub enum SandboxError {
+    #[error("the sandbox isn't supported on this system: {0}")]
+    Unsupported(String),
+
+    #[error("failed to sandbox `{0}`: {1}")]
+    Failed(PathBuf, String),
+}
+
+/// Runs module programs in the sandbox.
+pub fn enable() {
+    ENABLED.store(true, Ordering::SeqCst);
+}
+
+/// Checks whether module programs are run in the sandbox, as requested with
+/// `--sandbox`, the `ASIMOV_SANDBOX` environment variable, or the `sandbox`
+/// configuration setting.
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+        || std::env::var("ASIMOV_SANDBOX").is_ok_and(|v| v == "1" || v == "true")
+        || Config::get().is_ok_and(|config| config.sandbox)
+}
+
+/// Sets up the given command, running the program at the given path, to run
+/// in the sandbox with the capabilities declared by the program's module.
+/// Programs not declared by any module get the default capabilities.
+pub fn sandbox(
+    command: &mut Command,
+    program: &Path,
+    module: Option<&InstalledModule>,
+) -> Result<(), SandboxError> {
+    let failed = |e: String| SandboxError::Failed(program.to_path_buf(), e);
+
+    let default = Capabilities::default();
+    let capabilities = module.map_or(&default, |module| &module.extensions.capabilities);
+
+    let output_dir = std::env::current_dir().map_err(|e| failed(e.to_string()))?;
+    let writable = [output_dir, std::env::temp_dir()]
+        .into_iter()
+        .chain(capabilities.write.iter().cloned())
+        .collect::<Vec<_>>();
+
+    #[cfg(target_os = "linux")]
+    {
+        let unsupported_or_failed = |e: std::io::Error| match e.kind() {
+            std::io::ErrorKind::Unsupported => SandboxError::Unsupported(e.to_string()),
+            _ => failed(e.to_string()),
+        };
+        // The namespaces must be set up before Landlock is enforced, which
+        // would deny writing the ID mappings:
+        if !capabilities.network {
+            linux::deny_network(command).map_err(unsupported_or_failed)?;
+        }
+        linux::restrict_writes(command, &writable).map_err(unsupported_or_failed)?;
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (command, writable);
+        Err(SandboxError::Unsupported(
+            "only Linux is supported".to_string(),
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{
+        ffi::CStr,
+        fs::OpenOptions,
+        io,
+        os::{
+            fd::{AsRawFd, FromRawFd, OwnedFd},
+            unix::{fs::OpenOptionsExt, process::CommandExt},
+        },
+        path::{Path, PathBuf},
+        process::Command,
+        sync::OnceLock,
+    };
+
+    // See <linux/landlock.h>:
+    const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1 << 0;
+    const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const LANDLOCK_ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+    const LANDLOCK_ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+    const LANDLOCK_ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+    const LANDLOCK_ACCESS_FS_MAKE_CHAR: u64 = 1 << 6;
+    const LANDLOCK_ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+    const LANDLOCK_ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+    const LANDLOCK_ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
+    const LANDLOCK_ACCESS_FS_MAKE_FIFO: u64 = 1 << 10;
+    const LANDLOCK_ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
+    const LANDLOCK_ACCESS_FS_MAKE_SYM: u64 = 1 << 12;
+    const LANDLOCK_ACCESS_FS_REFER: u64 = 1 << 13; // ABI version 2
+    const LANDLOCK_ACCESS_FS_TRUNCATE: u64 = 1 << 14; // ABI version 3
+
+    /// The access rights that modify the file system, in ABI version 1.
+    const WRITE_ACCESS: u64 = LANDLOCK_ACCESS_FS_WRITE_FILE
+        | LANDLOCK_ACCESS_FS_REMOVE_DIR
+        | LANDLOCK_ACCESS_FS_REMOVE_FILE
+        | LANDLOCK_ACCESS_FS_MAKE_CHAR
+        | LANDLOCK_ACCESS_FS_MAKE_DIR
+        | LANDLOCK_ACCESS_FS_MAKE_REG
+        | LANDLOCK_ACCESS_FS_MAKE_SOCK
+        | LANDLOCK_ACCESS_FS_MAKE_FIFO
+        | LANDLOCK_ACCESS_FS_MAKE_BLOCK
+        | LANDLOCK_ACCESS_FS_MAKE_SYM;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// Restricts the program run by the given command to writing beneath
+    /// the given paths.
+    ///
+    /// The ruleset is built here, so that errors can be reported properly,
+    /// and only enforced in the child process right before it's executed.
+    pub(super) fn restrict_writes(command: &mut Command, writable: &[PathBuf]) -> io::Result<()> {
+        let abi = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<RulesetAttr>(),
+                0usize,
+                LANDLOCK_CREATE_RULESET_VERSION,
+            )
+        };
+        if abi < 1 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Landlock isn't available",
+            ));
+        }
+
+        let mut handled = WRITE_ACCESS;
+        if abi >= 2 {
+            handled |= LANDLOCK_ACCESS_FS_REFER;
+        }
+        let mut file_access = LANDLOCK_ACCESS_FS_WRITE_FILE;
+        if abi >= 3 {
+            handled |= LANDLOCK_ACCESS_FS_TRUNCATE;
+            file_access |= LANDLOCK_ACCESS_FS_TRUNCATE;
+        }
+
+        let attr = RulesetAttr {
+            handled_access_fs: handled,
+        };
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                size_of::<RulesetAttr>(),
+                0u32,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as libc::c_int) };
+
+        for path in writable {
+            add_rule(&ruleset, path, handled, file_access)?;
+        }
+        // Devices such as `/dev/null` and the terminal must stay writable:
+        add_rule(&ruleset, Path::new("/dev"), file_access, file_access)?;
+
+        unsafe {
+            command.pre_exec(move || {
+                if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0u32) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Allows the given access beneath the given path, or only the given
+    /// file access if the path is a file. Missing paths are skipped.
+    fn add_rule(ruleset: &OwnedFd, path: &Path, access: u64, file_access: u64) -> io::Result<()> {
+        let file = match OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_PATH | libc::O_CLOEXEC)
+            .open(path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        let attr = PathBeneathAttr {
+            allowed_access: if path.is_dir() { access } else { file_access },
+            parent_fd: file.as_raw_fd(),
+        };
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_add_rule,
+                ruleset.as_raw_fd(),
+                LANDLOCK_RULE_PATH_BENEATH,
+                &attr as *const PathBeneathAttr,
+                0u32,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Runs the program run by the given command in new user and network
+    /// namespaces, which leaves it without network access. Its user and
+    /// group IDs are mapped to themselves, so that it can still create and
+    /// read its files as usual.
+    pub(super) fn deny_network(command: &mut Command) -> io::Result<()> {
+        if !namespaces_available() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "unprivileged user namespaces aren't available",
+            ));
+        }
+
+        // The mappings are formatted here, as allocating isn't safe in the
+        // child process:
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let uid_map = format!("{} {} 1\n", uid, uid);
+        let gid_map = format!("{} {} 1\n", gid, gid);
+
+        unsafe {
+            command.pre_exec(move || {
+                if libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                // Mapping the group ID requires denying `setgroups` first:
+                write_file(c"/proc/self/setgroups", b"deny")?;
+                write_file(c"/proc/self/uid_map", uid_map.as_bytes())?;
+                write_file(c"/proc/self/gid_map", gid_map.as_bytes())?;
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks whether this process may create user and network namespaces,
+    /// which may be disabled for unprivileged users. This is checked in a
+    /// child process, once per invocation.
+    fn namespaces_available() -> bool {
+        static AVAILABLE: OnceLock<bool> = OnceLock::new();
+        *AVAILABLE.get_or_init(|| unsafe {
+            let pid = libc::fork();
+            if pid < 0 {
+                return false;
+            }
+            if pid == 0 {
+                let status = libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET);
+                libc::_exit(if status == 0 { 0 } else { 1 });
+            }
+            let mut status = 0;
+            while libc::waitpid(pid, &mut status, 0) < 0 {
+                if io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                    return false;
+                }
+            }
+            libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0
+        })
+    }
+
+    /// Writes the given contents to the file at the given path. This is
+    /// async-signal-safe.
+    fn write_file(path: &CStr, contents: &[u8]) -> io::Result<()> {
+        unsafe {
+            let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let written = libc::write(fd, contents.as_ptr().cast(), contents.len());
+            let result = if written == contents.len() as isize {
+                Ok(())
+            } else {
+                Err(io::Error::last_os_error())
+            };
+            libc::close(fd);
+            result
+        }
+    }
+}
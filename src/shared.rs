@@ -1,36 +1,544 @@
 // This is free and unencumbered software released into the public domain.
 
-use crate::Result;
+use crate::{
+    cache::ManifestCache, config::Config, input::InputError, limits::Limits, throttle::RateLimits,
+    Result, StandardOptions,
+};
 use asimov_env::paths::asimov_root;
 use asimov_module::{models::ModuleManifest, resolve::Resolver};
-use clientele::{Subcommand, SubcommandsProvider, SysexitsError::*};
+use clientele::{
+    crates::clap::ColorChoice,
+    Subcommand, SubcommandsProvider,
+    SysexitsError::{self, *},
+};
 use miette::{miette, IntoDiagnostic};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::IsTerminal,
+    path::{Path, PathBuf},
+    sync::Once,
+};
 
-pub(crate) fn build_resolver(pattern: &str) -> miette::Result<Resolver> {
-    let mut resolver = Resolver::new();
+/// A module manifest found in the module manifest directory.
+#[derive(Debug)]
+pub struct InstalledModule {
+    /// The path of the manifest file.
+    pub path: PathBuf,
+
+    /// The manifest itself.
+    pub manifest: ModuleManifest,
+
+    /// The manifest keys specific to this program, which `ModuleManifest`
+    /// doesn't know about.
+    pub extensions: ManifestExtensions,
+}
+
+impl InstalledModule {
+    /// Returns the ASIMOV root directory the module is installed in, which
+    /// is either the global or a project-local one.
+    pub fn root(&self) -> Option<&Path> {
+        self.path
+            .ancestors()
+            .find(|dir| dir.file_name().is_some_and(|name| name == "modules"))
+            .and_then(Path::parent)
+    }
+
+    /// Returns the directory of the module, if it's installed in a directory
+    /// of its own (i.e., `modules/<name>/module.yaml`).
+    pub fn module_dir(&self) -> Option<&Path> {
+        module_dir_of(&self.path)
+    }
+
+    /// Returns the path of the given program of the module, if it's installed
+    /// alongside the manifest in the module's directory or in the `libexec`
+    /// directory of the module's root directory.
+    pub fn program_path(&self, program: &str) -> Option<PathBuf> {
+        let libexec = self.root()?.join("libexec");
+        let dirs = self.module_dir().into_iter().chain([libexec.as_path()]);
+        dirs.flat_map(|dir| {
+            [program.to_string(), format!("{}.exe", program)].map(|filename| dir.join(filename))
+        })
+        .find(|path| path.is_file())
+    }
+
+    /// Checks whether the module is compatible with this program, returning
+    /// an explanation if it isn't.
+    pub fn check_compatibility(&self) -> std::result::Result<(), String> {
+        if let Some(version) = &self.extensions.version {
+            semver::Version::parse(version)
+                .map_err(|e| format!("invalid module version `{}`: {}", version, e))?;
+        }
+
+        if let Some(requirement) = &self.extensions.requires.asimov_cli {
+            let requirement = semver::VersionReq::parse(requirement).map_err(|e| {
+                format!(
+                    "invalid asimov-cli version requirement `{}`: {}",
+                    requirement, e
+                )
+            })?;
+            let version = semver::Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+
+            // Requirements without pre-release tags never match pre-release
+            // versions, which would rule out all development builds:
+            let mut release = version.clone();
+            release.pre = semver::Prerelease::EMPTY;
+
+            if !requirement.matches(&version) && !requirement.matches(&release) {
+                return Err(format!(
+                    "requires asimov-cli {}, but this is version {}",
+                    requirement, version
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The manifest keys specific to this program.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ManifestExtensions {
+    /// How the standard options are passed on to the module's programs.
+    pub flags: FlagConvention,
+
+    /// The SHA-256 checksums of the module's programs, in hexadecimal.
+    pub checksums: BTreeMap<String, String>,
+
+    /// The version of the module, e.g., `1.2.0`.
+    pub version: Option<String>,
+
+    /// The versions of other software the module requires.
+    pub requires: Requirements,
+
+    /// What the module's programs may do when run in the sandbox.
+    pub capabilities: Capabilities,
+
+    /// The resource limits for the module's programs.
+    pub limits: Limits,
+
+    /// The per-host limits on running the module's programs.
+    pub rate_limits: RateLimits,
+
+    /// The exit codes of the module's programs that indicate a transient
+    /// failure worth retrying. Defaults to `EX_TEMPFAIL` and `EX_UNAVAILABLE`.
+    pub retriable: Option<Vec<i32>>,
+}
+
+impl ManifestExtensions {
+    /// Checks whether the given exit code of the module's programs indicates
+    /// a transient failure.
+    pub fn is_retriable(&self, code: SysexitsError) -> bool {
+        match &self.retriable {
+            Some(codes) => codes.contains(&code.as_i32()),
+            None => matches!(code, EX_TEMPFAIL | EX_UNAVAILABLE),
+        }
+    }
+}
+
+/// What a module's programs may do when run in the sandbox (see
+/// [`crate::sandbox`]).
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Capabilities {
+    /// Whether the programs need network access, which is the default.
+    pub network: bool,
+
+    /// The paths the programs may write to, besides the output directory.
+    pub write: Vec<PathBuf>,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            network: true,
+            write: Vec::new(),
+        }
+    }
+}
+
+/// The versions of other software a module requires.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Requirements {
+    /// The versions of this program the module is compatible with, e.g.,
+    /// `>=25.0.0, <26.0.0`.
+    #[serde(rename = "asimov-cli")]
+    pub asimov_cli: Option<String>,
+}
+
+/// The convention a module follows for accepting the standard options.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FlagConvention {
+    /// The module's programs don't accept any standard options.
+    #[default]
+    None,
+
+    /// The module's programs accept `--verbose` (repeatable) and `--debug`.
+    Standard,
+}
+
+impl FlagConvention {
+    /// Returns the arguments forwarding the given options, if any.
+    pub fn args(&self, flags: &StandardOptions) -> Vec<String> {
+        let mut args = Vec::new();
+        if *self == FlagConvention::Standard {
+            for _ in 0..flags.verbose {
+                args.push("--verbose".to_string());
+            }
+            if flags.debug {
+                args.push("--debug".to_string());
+            }
+        }
+        args
+    }
+}
+
+/// The modules that provide programs of a given kind.
+pub struct ModuleSet {
+    pub resolver: Resolver,
+    pub modules: Vec<InstalledModule>,
+}
+
+impl ModuleSet {
+    /// Returns the installed module with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<&InstalledModule> {
+        self.modules.iter().find(|m| m.manifest.name == name)
+    }
+
+    /// Resolves the name of the module to handle the given URL: the first
+    /// module matching the URL itself or, failing that for a `file:` URL,
+    /// the first module handling the media type of the file.
+    pub fn resolve(&self, url: &str) -> Result<Option<String>> {
+        if let Some(module) = self.resolver.resolve(url)?.first() {
+            return Ok(Some(module.name.clone()));
+        }
 
-    let module_dir_path = asimov_root().join("modules");
-    let module_dir = std::fs::read_dir(&module_dir_path)
+        Ok(crate::input::file_media_type(url)
+            .and_then(|media_type| self.find_by_media_type(media_type))
+            .map(|module| module.manifest.name.clone()))
+    }
+
+    /// Returns the first module handling the given media type, if any.
+    pub fn find_by_media_type(&self, media_type: &str) -> Option<&InstalledModule> {
+        self.modules.iter().find(|m| {
+            m.manifest
+                .handles
+                .content_types
+                .iter()
+                .any(|t| t.eq_ignore_ascii_case(media_type))
+        })
+    }
+}
+
+/// Checks whether the given string looks like a media type (e.g., `text/html`)
+/// rather than a URL.
+pub fn is_media_type(input: &str) -> bool {
+    const TOP_LEVEL_TYPES: &[&str] = &[
+        "application",
+        "audio",
+        "font",
+        "image",
+        "message",
+        "model",
+        "multipart",
+        "text",
+        "video",
+    ];
+    input.split_once('/').is_some_and(|(kind, subtype)| {
+        TOP_LEVEL_TYPES.contains(&kind.to_ascii_lowercase().as_str())
+            && !subtype.is_empty()
+            && !subtype.contains('/')
+    })
+}
+
+/// Returns the given URLs, replacing `-` with the URLs read from stdin, one
+/// per line, such as the output of `asimov catalog`. Blank lines and lines
+/// starting with `#` are skipped.
+pub(crate) fn read_urls(urls: &[String]) -> Result<Vec<String>> {
+    let mut result = Vec::with_capacity(urls.len());
+    for url in urls {
+        if url != "-" {
+            result.push(url.clone());
+            continue;
+        }
+        for line in std::io::stdin().lines() {
+            let line = line?;
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                result.push(line.to_string());
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Normalizes the given input to a URL (see [`crate::input::normalize`]),
+/// or prints an error.
+pub(crate) fn normalize_input(input: &str) -> Result<String> {
+    crate::input::normalize(input).map_err(|e| {
+        let code = match e {
+            InputError::FileNotFound(_) => EX_NOINPUT,
+            _ => EX_USAGE,
+        };
+        eprintln!("{:?}", miette::Report::new(e));
+        code
+    })
+}
+
+/// Returns the arguments for invoking a program of the named module on `url`:
+/// the forwarded standard options, followed by `args`, followed by `url`.
+pub(crate) fn module_args(
+    modules: &ModuleSet,
+    name: &str,
+    args: &[String],
+    url: &str,
+    flags: &StandardOptions,
+) -> Vec<String> {
+    let mut result = modules
+        .get(name)
+        .map(|module| module.extensions.flags.args(flags))
+        .unwrap_or_default();
+    result.extend_from_slice(args);
+    result.push(url.to_string());
+    result
+}
+
+/// Returns the module manifest directory.
+pub fn module_dir() -> PathBuf {
+    asimov_root().join("modules")
+}
+
+/// Returns the directory holding the manifests of disabled modules, which
+/// are ignored when resolving modules.
+pub fn disabled_module_dir() -> PathBuf {
+    module_dir().join("disabled")
+}
+
+/// Returns the project-local ASIMOV directory, if any: the nearest `.asimov`
+/// directory with a `modules` subdirectory, walking up from the current
+/// directory. The global ASIMOV root directory doesn't count.
+///
+/// As the programs of its modules get run, it's only used if the project
+/// directory containing it is listed under `trusted_projects` in the
+/// configuration, so that working in an untrusted checkout is safe.
+pub fn project_root() -> Option<PathBuf> {
+    let global = asimov_root().canonicalize().ok();
+    let root = std::env::current_dir()
+        .ok()?
+        .ancestors()
+        .map(|dir| dir.join(".asimov"))
+        .find(|dir| dir.join("modules").is_dir() && dir.canonicalize().ok() != global)?;
+
+    let project = root.parent()?.canonicalize().ok()?;
+    let is_trusted = Config::get().is_ok_and(|config| {
+        config
+            .trusted_projects
+            .iter()
+            .any(|dir| dir.canonicalize().is_ok_and(|dir| dir == project))
+    });
+    if !is_trusted {
+        static WARNED: Once = Once::new();
+        WARNED.call_once(|| {
+            eprintln!(
+                "{}: ignoring the modules in `{}`, as the project isn't listed under `trusted_projects` in `{}`",
+                "asimov",
+                root.display(),
+                Config::path().display()
+            );
+        });
+        return None;
+    }
+    Some(root)
+}
+
+/// Reads all module manifests in the module manifest directory, overlaid
+/// with those in the project-local one (see [`project_root`]), which take
+/// precedence over global ones of the same name.
+pub(crate) fn read_manifests() -> miette::Result<Vec<InstalledModule>> {
+    let project = project_root();
+
+    // A fresh ASIMOV root directory has no modules yet:
+    let global_dir = module_dir();
+    let mut modules = if !global_dir.exists() {
+        Vec::new()
+    } else {
+        read_manifests_in(&global_dir)?
+    };
+
+    if let Some(project) = project {
+        for module in read_manifests_in(&project.join("modules"))? {
+            modules.retain(|m| m.manifest.name != module.manifest.name);
+            modules.push(module);
+        }
+    }
+
+    Ok(modules)
+}
+
+/// Reads all module manifests of disabled modules, overlaid with those of
+/// disabled modules in the project-local directory (see [`project_root`]).
+pub(crate) fn read_disabled_manifests() -> miette::Result<Vec<InstalledModule>> {
+    let dirs = [
+        Some(disabled_module_dir()),
+        project_root().map(|project| project.join("modules").join("disabled")),
+    ];
+
+    let mut modules: Vec<InstalledModule> = Vec::new();
+    for dir in dirs.into_iter().flatten().filter(|dir| dir.exists()) {
+        for module in read_manifests_in(&dir)? {
+            modules.retain(|m| m.manifest.name != module.manifest.name);
+            modules.push(module);
+        }
+    }
+    Ok(modules)
+}
+
+/// The file names of the manifest of a module installed in a directory of
+/// its own, along with its programs.
+const MODULE_MANIFEST_NAMES: [&str; 2] = ["module.yaml", "module.yml"];
+
+/// Reads all module manifests in the given directory, recursively, using
+/// the manifest cache unless `ASIMOV_NO_CACHE` is set.
+fn read_manifests_in(module_dir_path: &Path) -> miette::Result<Vec<InstalledModule>> {
+    let paths = manifest_paths_in(module_dir_path)?;
+    if std::env::var_os("ASIMOV_NO_CACHE").is_some() {
+        return paths.iter().map(|path| read_manifest(path)).collect();
+    }
+
+    let cache_path = ManifestCache::path();
+    let mut cache = ManifestCache::load(&cache_path);
+    let modules = paths
+        .iter()
+        .map(|path| {
+            let value = cache.get_or_read(path, read_manifest_value)?;
+            manifest_from_value(path, value)
+        })
+        .collect::<miette::Result<Vec<_>>>()?;
+    // The cache is merely an optimization, so failing to save it is fine:
+    let _ = cache.save(&cache_path);
+
+    Ok(modules)
+}
+
+/// Finds all module manifests in the given directory, recursively.
+///
+/// A subdirectory with a `module.yaml` (or `module.yml`) manifest is the
+/// directory of a single module, which isn't searched any further. Other
+/// subdirectories are searched for manifests in turn, except for the
+/// `disabled` directory of a module manifest directory.
+fn manifest_paths_in(dir: &Path) -> miette::Result<Vec<PathBuf>> {
+    let entries = std::fs::read_dir(dir)
         .map_err(|e| miette!("Failed to read module manifest directory: {e}"))?
         .filter_map(Result::ok);
 
-    for entry in module_dir {
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry.path();
         let filename = entry.file_name();
         let filename = filename.to_string_lossy();
-        if !filename.ends_with(".yml") && !filename.ends_with(".yaml") {
-            continue;
+        if path.is_dir() {
+            if filename == "disabled" && dir.file_name().is_some_and(|name| name == "modules") {
+                continue;
+            }
+            let manifest = MODULE_MANIFEST_NAMES
+                .iter()
+                .map(|name| path.join(name))
+                .find(|path| path.is_file());
+            match manifest {
+                Some(manifest) => paths.push(manifest),
+                None => paths.extend(manifest_paths_in(&path)?),
+            }
+        } else if filename.ends_with(".yml") || filename.ends_with(".yaml") {
+            paths.push(path);
         }
-        let file = std::fs::File::open(entry.path()).into_diagnostic()?;
+    }
+    paths.sort();
 
-        let manifest: ModuleManifest = serde_yml::from_reader(file).map_err(|e| {
-            miette!(
-                "Invalid module manifest at `{}`: {}",
-                entry.path().display(),
-                e
-            )
-        })?;
+    Ok(paths)
+}
+
+/// Returns the module directory for the given manifest path, if the manifest
+/// is that of a module installed in a directory of its own.
+fn module_dir_of(manifest_path: &Path) -> Option<&Path> {
+    let filename = manifest_path.file_name()?.to_str()?;
+    let dir = manifest_path.parent()?;
+    (MODULE_MANIFEST_NAMES.contains(&filename)
+        && dir.file_name().is_none_or(|name| name != "modules"))
+    .then_some(dir)
+}
+
+/// Finds the installed module with the given name, returning it and whether
+/// it's enabled.
+pub(crate) fn find_module(name: &str) -> miette::Result<Option<(InstalledModule, bool)>> {
+    let enabled = read_manifests()?.into_iter().map(|module| (module, true));
+    let disabled = read_disabled_manifests()?
+        .into_iter()
+        .map(|module| (module, false));
+    Ok(enabled
+        .chain(disabled)
+        .find(|(module, _)| module.manifest.name == name))
+}
+
+/// Finds the installed and enabled module declaring the program at the given
+/// path.
+pub(crate) fn find_program_module(program: &Path) -> miette::Result<Option<InstalledModule>> {
+    let Some(name) = program.file_stem().and_then(|stem| stem.to_str()) else {
+        return Ok(None);
+    };
+    Ok(read_manifests()?
+        .into_iter()
+        .find(|module| module.manifest.provides.programs.iter().any(|p| p == name)))
+}
 
-        if !manifest
+/// Reads the module manifest at the given path.
+pub(crate) fn read_manifest(path: &Path) -> miette::Result<InstalledModule> {
+    manifest_from_value(path, read_manifest_value(path)?)
+}
+
+fn read_manifest_value(path: &Path) -> miette::Result<serde_yml::Value> {
+    let file = std::fs::File::open(path).into_diagnostic()?;
+    serde_yml::from_reader(file)
+        .map_err(|e| miette!("Invalid module manifest at `{}`: {}", path.display(), e))
+}
+
+fn manifest_from_value(path: &Path, value: serde_yml::Value) -> miette::Result<InstalledModule> {
+    let invalid =
+        |e: serde_yml::Error| miette!("Invalid module manifest at `{}`: {}", path.display(), e);
+
+    let manifest: ModuleManifest = serde_yml::from_value(value.clone()).map_err(invalid)?;
+    let extensions: ManifestExtensions = serde_yml::from_value(value).map_err(invalid)?;
+
+    Ok(InstalledModule {
+        path: path.to_path_buf(),
+        manifest,
+        extensions,
+    })
+}
+
+/// Returns the kinds of programs (e.g., `fetcher` for `*-fetcher` programs)
+/// provided by all installed modules.
+pub(crate) fn program_kinds() -> miette::Result<BTreeSet<String>> {
+    Ok(read_manifests()?
+        .iter()
+        .flat_map(|module| module.manifest.provides.programs.iter())
+        .filter_map(|program| program.rsplit_once('-'))
+        .map(|(_, kind)| kind.to_string())
+        .collect())
+}
+
+/// Builds a resolver over the modules providing programs of the given kind
+/// (e.g., `fetcher` for `*-fetcher` programs). Incompatible modules are
+/// skipped with a warning.
+pub(crate) fn build_resolver(pattern: &str) -> miette::Result<ModuleSet> {
+    let mut resolver = Resolver::new();
+    let mut modules = Vec::new();
+
+    for module in read_manifests()? {
+        if !module
+            .manifest
             .provides
             .programs
             .iter()
@@ -39,19 +547,46 @@ pub(crate) fn build_resolver(pattern: &str) -> miette::Result<Resolver> {
             continue;
         }
 
+        if let Err(reason) = module.check_compatibility() {
+            color_print::ceprintln!(
+                "<s,y>warning:</> skipping the module `{}`: {}",
+                module.manifest.name,
+                reason
+            );
+            continue;
+        }
+
         resolver
-            .insert_manifest(&manifest)
+            .insert_manifest(&module.manifest)
             .map_err(|e| miette!("{e}"))?;
+        modules.push(module);
     }
 
-    Ok(resolver)
+    Ok(ModuleSet { resolver, modules })
 }
 
-/// Locates the given subcommand or prints an error.
+/// Locates the given subcommand or prints an error. If verification is
+/// required (see [`crate::verify::is_required`]), the subcommand must also
+/// pass verification.
 pub fn locate_subcommand(name: &str) -> Result<Subcommand> {
-    let libexec = asimov_root().join("libexec");
-    if libexec.exists() {
-        let file = std::fs::read_dir(libexec)?
+    let cmd = find_subcommand(name)?;
+
+    if crate::verify::is_required() {
+        crate::verify::verify_program(&cmd.path).map_err(|e| {
+            eprintln!("{}: refusing to run {}: {}", "asimov", cmd.name, e);
+            EX_NOPERM
+        })?;
+    }
+
+    Ok(cmd)
+}
+
+fn find_subcommand(name: &str) -> Result<Subcommand> {
+    for program_dir in program_dirs() {
+        if !program_dir.exists() {
+            continue;
+        }
+        let file = std::fs::read_dir(program_dir)?
             .filter_map(Result::ok)
             .find(|entry| {
                 entry.file_name().to_str().is_some_and(|filename| {
@@ -74,3 +609,68 @@ pub fn locate_subcommand(name: &str) -> Result<Subcommand> {
         }
     }
 }
+
+/// Returns the directories that programs are looked up in, in order of
+/// precedence: for the project-local and then the global ASIMOV root
+/// directory, the `libexec` directory followed by the directories of modules
+/// installed in a directory of their own.
+fn program_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for root in project_root().into_iter().chain([asimov_root()]) {
+        dirs.push(root.join("libexec"));
+        if let Ok(manifests) = manifest_paths_in(&root.join("modules")) {
+            dirs.extend(
+                manifests
+                    .iter()
+                    .filter_map(|path| module_dir_of(path))
+                    .map(Path::to_path_buf),
+            );
+        }
+    }
+    dirs
+}
+
+/// Returns the environment variables exported to every module subprocess.
+///
+/// These form a stable contract that modules can rely on to learn that they
+/// were invoked via `asimov` and how the parent was configured:
+///
+/// - `ASIMOV_ROOT`: the ASIMOV root directory.
+/// - `ASIMOV_CLI_VERSION`: the version of this program.
+/// - `ASIMOV_VERBOSE`: the verbosity level, i.e., the number of `-v` flags.
+/// - `ASIMOV_DEBUG`: `1` if debug output is enabled, `0` otherwise.
+/// - `ASIMOV_COLOR`: `always` if colored output is enabled, `never` otherwise.
+pub fn module_env(
+    is_debug: bool,
+    verbosity: u8,
+    color: ColorChoice,
+) -> Vec<(&'static str, String)> {
+    vec![
+        ("ASIMOV_ROOT", asimov_root().display().to_string()),
+        ("ASIMOV_CLI_VERSION", env!("CARGO_PKG_VERSION").to_string()),
+        ("ASIMOV_VERBOSE", verbosity.to_string()),
+        ("ASIMOV_DEBUG", if is_debug { "1" } else { "0" }.to_string()),
+        ("ASIMOV_COLOR", color_choice(color).to_string()),
+    ]
+}
+
+/// Determines whether colored output is enabled for this process, as given
+/// with `--color` or, if that's `auto`, by the environment and the terminal.
+fn color_choice(color: ColorChoice) -> &'static str {
+    match color {
+        ColorChoice::Always => return "always",
+        ColorChoice::Never => return "never",
+        ColorChoice::Auto => {}
+    }
+    if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return "never";
+    }
+    if std::env::var_os("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0") {
+        return "always";
+    }
+    if std::io::stdout().is_terminal() {
+        "always"
+    } else {
+        "never"
+    }
+}
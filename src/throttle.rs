@@ -0,0 +1,260 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Per-host throttling of module programs, so that batch jobs are polite to
+//! the hosts they process URLs from.
+//!
+//! The limits are keyed on the host of the URL, and are enforced across all
+//! concurrently running invocations of this program (e.g., with `xargs -P`),
+//! which coordinate through lock files in the `run/hosts` directory of the
+//! ASIMOV root directory. Cross-process coordination is only supported on
+//! Unix; elsewhere, only the request rate of each invocation is limited, as
+//! is the case wherever that directory can't be written (see
+//! [`acquire_local`]).
+//!
+//! Limits are configured under `rate_limits` in the configuration and in a
+//! module's manifest, where the stricter one applies. Unless configured, at
+//! most [`DEFAULT_CONCURRENCY`] programs run for the same host at a time,
+//! starting at most [`DEFAULT_REQUESTS_PER_SECOND`] per second. A limit of
+//! zero means no limit.
+
+use crate::{config::Config, shared::InstalledModule};
+use asimov_env::paths::asimov_root;
+use serde::{de::Error as _, Deserialize, Deserializer};
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// The default maximum number of programs running for the same host.
+pub const DEFAULT_CONCURRENCY: u32 = 2;
+
+/// The default maximum number of programs started per second for the same
+/// host.
+pub const DEFAULT_REQUESTS_PER_SECOND: f64 = 5.0;
+
+/// How long to wait before checking again for a free concurrency slot.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Per-host limits on running module programs.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RateLimits {
+    /// The maximum number of programs running for the same host at a time.
+    pub concurrency: Option<u32>,
+
+    /// The maximum number of programs started per second for the same host.
+    #[serde(deserialize_with = "deserialize_rate")]
+    pub requests_per_second: Option<f64>,
+}
+
+impl RateLimits {
+    /// Returns the strictest combination of both limits, where no limit
+    /// (zero) is the least strict.
+    pub fn min(self, other: Self) -> Self {
+        fn min<T: PartialOrd + Default>(a: Option<T>, b: Option<T>) -> Option<T> {
+            let unlimited = T::default();
+            match (a, b) {
+                (Some(a), Some(b)) if a == unlimited => Some(b),
+                (Some(a), Some(b)) if b == unlimited => Some(a),
+                (Some(a), Some(b)) => Some(if a < b { a } else { b }),
+                (a, b) => a.or(b),
+            }
+        }
+        Self {
+            concurrency: min(self.concurrency, other.concurrency),
+            requests_per_second: min(self.requests_per_second, other.requests_per_second),
+        }
+    }
+
+    /// Returns these limits with the defaults filled in.
+    pub fn or_default(self) -> Self {
+        Self {
+            concurrency: self.concurrency.or(Some(DEFAULT_CONCURRENCY)),
+            requests_per_second: self
+                .requests_per_second
+                .or(Some(DEFAULT_REQUESTS_PER_SECOND)),
+        }
+    }
+}
+
+/// Returns the limits for running programs of the given modules, combining
+/// the configured limits (or the defaults) with those of the modules.
+pub fn limits_for<'a>(
+    modules: impl IntoIterator<Item = &'a InstalledModule>,
+) -> miette::Result<RateLimits> {
+    let configured = Config::get()?.rate_limits.or_default();
+    Ok(modules.into_iter().fold(configured, |limits, module| {
+        limits.min(module.extensions.rate_limits)
+    }))
+}
+
+/// Permission to run a program for a host, which is held until dropped.
+pub struct Permit {
+    _slot: Option<File>,
+}
+
+/// Waits until the limits allow running a program for the host of the given
+/// URL, returning a permit to be held while the program runs. URLs without
+/// a host (e.g., `file:` URLs) aren't throttled.
+pub fn acquire(url: &str, limits: &RateLimits) -> io::Result<Permit> {
+    let Some(host) = host_of(url) else {
+        return Ok(Permit { _slot: None });
+    };
+
+    let dir = host_dir();
+    std::fs::create_dir_all(&dir)?;
+
+    let slot = match limits.concurrency {
+        Some(concurrency) if concurrency > 0 => Some(acquire_slot(&dir, &host, concurrency)?),
+        _ => None,
+    };
+    if let Some(rate) = limits.requests_per_second.filter(|rate| *rate > 0.0) {
+        wait_for_turn(&dir, &host, rate)?;
+    }
+
+    Ok(Permit { _slot: slot })
+}
+
+/// Waits until the limits allow running a program for the host of the given
+/// URL, as [`acquire`] does, but only coordinating with this invocation. As
+/// its programs are run one at a time, only the request rate is limited.
+pub fn acquire_local(url: &str, limits: &RateLimits) -> Permit {
+    static LAST_STARTS: Mutex<BTreeMap<String, Instant>> = Mutex::new(BTreeMap::new());
+
+    let rate = limits.requests_per_second.filter(|rate| *rate > 0.0);
+    if let (Some(host), Some(rate)) = (host_of(url), rate) {
+        let mut last_starts = LAST_STARTS.lock().unwrap_or_else(PoisonError::into_inner);
+        let interval = Duration::try_from_secs_f64(1.0 / rate).unwrap_or(Duration::MAX);
+        if let Some(last_start) = last_starts.get(&host) {
+            std::thread::sleep(interval.saturating_sub(last_start.elapsed()));
+        }
+        last_starts.insert(host, Instant::now());
+    }
+
+    Permit { _slot: None }
+}
+
+/// Returns the host of the given URL, made safe to use in file names.
+fn host_of(url: &str) -> Option<String> {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(sanitize))
+}
+
+fn host_dir() -> PathBuf {
+    asimov_root().join("run").join("hosts")
+}
+
+/// Makes the given host name safe to use in file names.
+fn sanitize(host: &str) -> String {
+    host.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Waits for one of the host's concurrency slots to become free, returning
+/// its lock file, which is locked until closed.
+fn acquire_slot(dir: &Path, host: &str, concurrency: u32) -> io::Result<File> {
+    loop {
+        for slot in 0..concurrency {
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(dir.join(format!("{}.{}.lock", host, slot)))?;
+            if lock(&file, false)? {
+                return Ok(file);
+            }
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Waits until the host's request rate allows starting another program,
+/// recording the start time for the next waiter.
+fn wait_for_turn(dir: &Path, host: &str, rate: f64) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(dir.join(format!("{}.rate", host)))?;
+    // Waiters are serialized, so each one sees its predecessor's start time:
+    lock(&file, true)?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let last_start = contents
+        .trim()
+        .parse::<u128>()
+        .ok()
+        .map(|nanos| UNIX_EPOCH + Duration::from_nanos(nanos as u64));
+
+    if let Some(last_start) = last_start {
+        let interval = Duration::try_from_secs_f64(1.0 / rate)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid request rate"))?;
+        let next_start = last_start + interval;
+        if let Ok(delay) = next_start.duration_since(SystemTime::now()) {
+            std::thread::sleep(delay);
+        }
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    file.set_len(0)?;
+    file.rewind()?;
+    write!(file, "{}", now)?;
+
+    // Closing the file releases the lock:
+    Ok(())
+}
+
+fn deserialize_rate<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    match Option::<f64>::deserialize(deserializer)? {
+        Some(rate) if !rate.is_finite() || rate < 0.0 => Err(D::Error::custom(format!(
+            "invalid request rate: `{}`",
+            rate
+        ))),
+        rate => Ok(rate),
+    }
+}
+
+/// Locks the given file exclusively, returning whether it got locked, which
+/// is always the case if `wait` is `true`.
+fn lock(file: &File, wait: bool) -> io::Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::fd::AsRawFd;
+
+        let operation = if wait {
+            libc::LOCK_EX
+        } else {
+            libc::LOCK_EX | libc::LOCK_NB
+        };
+        loop {
+            if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+                return Ok(true);
+            }
+            let error = io::Error::last_os_error();
+            match error.kind() {
+                io::ErrorKind::Interrupted => continue,
+                io::ErrorKind::WouldBlock => return Ok(false),
+                _ => return Err(error),
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (file, wait);
+        Ok(true)
+    }
+}
@@ -0,0 +1,146 @@
+// This is free and unencumbered software released into the public domain.
+
+//! Verification of module programs against the SHA-256 checksums recorded
+//! in module manifests and against minisign signatures.
+//!
+//! A manifest records checksums under its `checksums` key, mapping program
+//! names to hexadecimal SHA-256 digests. A program's signature is read from
+//! the `.minisig` file next to it, and is checked against the public keys
+//! listed under `trusted_keys` in the configuration.
+
+use crate::{config::Config, shared::read_manifests};
+use sha2::{Digest, Sha256};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use thiserror::Error;
+
+static REQUIRED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("checksum mismatch for `{path}`: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("invalid signature for `{0}`")]
+    InvalidSignature(PathBuf),
+
+    #[error("`{0}` has neither a recorded checksum nor a trusted signature")]
+    Unverifiable(PathBuf),
+
+    #[error("failed to verify `{0}`: {1}")]
+    Failed(PathBuf, String),
+}
+
+/// Requires module programs to be verified before they are run.
+pub fn require() {
+    REQUIRED.store(true, Ordering::SeqCst);
+}
+
+/// Checks whether module programs must be verified before they are run, as
+/// requested with `--verify`, the `ASIMOV_VERIFY` environment variable, or
+/// the `verify` configuration setting. If the configuration can't be loaded,
+/// verification is required, so as to fail closed.
+pub fn is_required() -> bool {
+    REQUIRED.load(Ordering::SeqCst)
+        || std::env::var("ASIMOV_VERIFY").is_ok_and(|v| v == "1" || v == "true")
+        || Config::get().map_or(true, |config| config.verify)
+}
+
+/// Verifies the installed program at the given path against the checksum
+/// recorded in the manifest of the module declaring it, and against its
+/// signature, failing if it can be verified by neither.
+pub fn verify_program(path: &Path) -> Result<(), VerifyError> {
+    let failed = |e: String| VerifyError::Failed(path.to_path_buf(), e);
+
+    let program = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| VerifyError::Unverifiable(path.to_path_buf()))?;
+    let expected = read_manifests()
+        .map_err(|e| failed(e.to_string()))?
+        .into_iter()
+        .find_map(|module| module.extensions.checksums.get(program).cloned());
+    let trusted_keys = &Config::get()
+        .map_err(|e| failed(e.to_string()))?
+        .trusted_keys;
+
+    if verify_file(path, expected.as_deref(), trusted_keys)? {
+        Ok(())
+    } else {
+        Err(VerifyError::Unverifiable(path.to_path_buf()))
+    }
+}
+
+/// Verifies the file at the given path against the expected checksum, if
+/// any, and against its signature, if there are both a `.minisig` file and
+/// trusted keys. Returns whether the file was verified by either.
+pub fn verify_file(
+    path: &Path,
+    expected_sha256: Option<&str>,
+    trusted_keys: &[String],
+) -> Result<bool, VerifyError> {
+    let failed = |e: String| VerifyError::Failed(path.to_path_buf(), e);
+    let mut verified = false;
+
+    if let Some(expected) = expected_sha256 {
+        let actual = sha256_file(path).map_err(|e| failed(e.to_string()))?;
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(VerifyError::ChecksumMismatch {
+                path: path.to_path_buf(),
+                expected: expected.to_string(),
+                actual,
+            });
+        }
+        verified = true;
+    }
+
+    let signature_path = signature_path(path);
+    if signature_path.is_file() && !trusted_keys.is_empty() {
+        let signature = std::fs::read_to_string(&signature_path)
+            .map_err(|e| failed(e.to_string()))
+            .and_then(|s| {
+                minisign_verify::Signature::decode(&s).map_err(|e| failed(e.to_string()))
+            })?;
+        let data = std::fs::read(path).map_err(|e| failed(e.to_string()))?;
+
+        let is_trusted = trusted_keys.iter().any(|key| {
+            minisign_verify::PublicKey::from_base64(key.trim())
+                .is_ok_and(|key| key.verify(&data, &signature, false).is_ok())
+        });
+        if !is_trusted {
+            return Err(VerifyError::InvalidSignature(path.to_path_buf()));
+        }
+        verified = true;
+    }
+
+    Ok(verified)
+}
+
+/// Returns the path of the signature file for the given file.
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut filename = path.file_name().unwrap_or_default().to_owned();
+    filename.push(".minisig");
+    path.with_file_name(filename)
+}
+
+/// Returns the hexadecimal SHA-256 digest of the file at the given path.
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 65536];
+    loop {
+        let len = file.read(&mut buffer)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&buffer[..len]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -0,0 +1,69 @@
+// This is free and unencumbered software released into the public domain.
+
+use asimov_cli::cache::ManifestCache;
+use std::{convert::Infallible, io::Result};
+use temp_dir::TempDir;
+
+#[test]
+pub fn test_manifest_cache() -> Result<()> {
+    let dir = TempDir::new()?;
+    let cache_path = dir.child("cache").join("manifests.json");
+    let manifest_path = dir.child("example.yaml");
+    std::fs::write(&manifest_path, "name: example\n")?;
+
+    let parse = |path: &std::path::Path| -> std::result::Result<_, Infallible> {
+        Ok(serde_yml::from_str(&std::fs::read_to_string(path).unwrap()).unwrap())
+    };
+    let unreachable = |_: &std::path::Path| -> std::result::Result<_, Infallible> {
+        panic!("the manifest should have been cached")
+    };
+
+    let mut cache = ManifestCache::load(&cache_path);
+    let manifest = cache.get_or_read(&manifest_path, parse).unwrap();
+    assert_eq!(manifest["name"], "example");
+    cache.save(&cache_path)?;
+
+    let mut cache = ManifestCache::load(&cache_path);
+    let cached = cache.get_or_read(&manifest_path, unreachable).unwrap();
+    assert_eq!(cached, manifest);
+
+    std::fs::write(&manifest_path, "name: modified\n")?;
+    let mut cache = ManifestCache::load(&cache_path);
+    let manifest = cache.get_or_read(&manifest_path, parse).unwrap();
+    assert_eq!(manifest["name"], "modified");
+
+    Ok(())
+}
+
+#[test]
+pub fn test_manifest_cache_concurrent_saves() -> Result<()> {
+    let dir = TempDir::new()?;
+    let cache_path = dir.child("cache").join("manifests.json");
+    let manifest_path = dir.child("example.yaml");
+    std::fs::write(&manifest_path, "name: example\n")?;
+
+    let parse = |path: &std::path::Path| -> std::result::Result<_, Infallible> {
+        Ok(serde_yml::from_str(&std::fs::read_to_string(path).unwrap()).unwrap())
+    };
+
+    std::thread::scope(|scope| {
+        let writers = (0..8)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut cache = ManifestCache::load(&cache_path);
+                    cache.get_or_read(&manifest_path, parse).unwrap();
+                    cache.save(&cache_path)
+                })
+            })
+            .collect::<Vec<_>>();
+        writers
+            .into_iter()
+            .try_for_each(|writer| writer.join().unwrap())
+    })?;
+
+    // Only the cache itself is left behind:
+    let files = std::fs::read_dir(dir.child("cache"))?.count();
+    assert_eq!(files, 1);
+
+    Ok(())
+}
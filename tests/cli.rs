@@ -0,0 +1,416 @@
+// This is free and unencumbered software released into the public domain.
+
+#![cfg(unix)]
+
+use std::{
+    io::{Result, Write},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+use temp_dir::TempDir;
+
+/// A temporary ASIMOV root directory, along with a working directory to
+/// run this program in.
+struct Root {
+    dir: TempDir,
+}
+
+impl Root {
+    fn new() -> Result<Self> {
+        let root = Self {
+            dir: TempDir::new()?,
+        };
+        std::fs::create_dir_all(root.path().join("modules"))?;
+        std::fs::create_dir_all(root.path().join("libexec"))?;
+        std::fs::create_dir_all(root.work_dir())?;
+        Ok(root)
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.child("root")
+    }
+
+    fn work_dir(&self) -> PathBuf {
+        self.dir.child("work")
+    }
+
+    /// Installs a module with the given manifest keys (besides its name and
+    /// programs) and programs, given as pairs of names and shell scripts.
+    fn add_module(&self, name: &str, manifest: &str, programs: &[(&str, &str)]) -> Result<()> {
+        write_module(&self.path(), name, manifest, programs)
+    }
+
+    fn asimov(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_asimov"));
+        command
+            .args(args)
+            .current_dir(self.work_dir())
+            .env("ASIMOV_ROOT", self.path())
+            .env("NO_COLOR", "1");
+        command
+    }
+
+    fn run(&self, args: &[&str]) -> Result<Output> {
+        self.asimov(args).stdin(Stdio::null()).output()
+    }
+
+    fn run_with_stdin(&self, args: &[&str], stdin: &[u8]) -> Result<Output> {
+        let mut child = self
+            .asimov(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        child.stdin.take().unwrap().write_all(stdin)?;
+        child.wait_with_output()
+    }
+}
+
+/// Installs a module in the given ASIMOV directory, with the given manifest
+/// keys (besides its name and programs) and programs, given as pairs of
+/// names and shell scripts.
+fn write_module(
+    asimov_dir: &Path,
+    name: &str,
+    manifest: &str,
+    programs: &[(&str, &str)],
+) -> Result<()> {
+    let programs_yaml = programs
+        .iter()
+        .map(|(program, _)| format!("    - asimov-{}-{}\n", name, program))
+        .collect::<String>();
+    std::fs::create_dir_all(asimov_dir.join("modules"))?;
+    std::fs::write(
+        asimov_dir.join("modules").join(format!("{}.yaml", name)),
+        format!(
+            "name: {name}\nlabel: {name}\nsummary: A test module.\nlinks: []\n\
+             provides:\n  programs:\n{programs_yaml}{manifest}"
+        ),
+    )?;
+    std::fs::create_dir_all(asimov_dir.join("libexec"))?;
+    for (program, script) in programs {
+        let path = asimov_dir
+            .join("libexec")
+            .join(format!("asimov-{}-{}", name, program));
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", script))?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+/// Writes a module, whose fetcher prints the given text, to a directory of
+/// the given name in the working directory, ready to be installed.
+fn write_module_source(root: &Root, name: &str, text: &str) -> Result<()> {
+    let dir = root.work_dir().join(name);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(
+        dir.join("module.yaml"),
+        format!(
+            "name: {name}\nlabel: {name}\nsummary: A test module.\nlinks: []\n\
+             provides:\n  programs:\n    - asimov-{name}-fetcher\n\
+             handles:\n  url_protocols:\n    - {name}\n"
+        ),
+    )?;
+    let program = dir.join(format!("asimov-{}-fetcher", name));
+    std::fs::write(&program, format!("#!/bin/sh\necho {}\n", text))?;
+    std::fs::set_permissions(&program, std::fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// A module fetching and importing `test:` URLs, whose importer prints the
+/// source URL and the data it read.
+fn add_test_module(root: &Root) -> Result<()> {
+    root.add_module(
+        "test",
+        "handles:\n  url_protocols:\n    - test\n",
+        &[
+            ("fetcher", r#"for url; do :; done; echo "fetched $url""#),
+            ("importer", r#"echo "imported $ASIMOV_SOURCE_URL:"; cat"#),
+        ],
+    )
+}
+
+#[test]
+pub fn test_fetch_import() -> Result<()> {
+    let root = Root::new()?;
+    add_test_module(&root)?;
+
+    let output = root.run(&["fetch", "--import", "test:example"])?;
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        stdout(&output),
+        "imported test:example:\nfetched test:example\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn test_import_from_stdin() -> Result<()> {
+    let root = Root::new()?;
+    add_test_module(&root)?;
+
+    let output = root.run_with_stdin(
+        &["import", "--from-stdin", "--as", "test:example"],
+        b"some data\n",
+    )?;
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&output), "imported test:example:\nsome data\n");
+
+    Ok(())
+}
+
+#[test]
+pub fn test_catalog() -> Result<()> {
+    let root = Root::new()?;
+    add_test_module(&root)?;
+    root.add_module(
+        "listing",
+        "handles:\n  url_protocols:\n    - listing\n",
+        &[("cataloger", "echo test:a; echo; echo test:b")],
+    )?;
+
+    let output = root.run(&["catalog", "listing:example"])?;
+    assert!(output.status.success(), "{:?}", output);
+    let listing = stdout(&output);
+    assert_eq!(listing, "test:a\n\ntest:b\n");
+
+    let output = root.run(&["catalog", "--json", "listing:example"])?;
+    assert!(output.status.success(), "{:?}", output);
+    let json: Vec<String> = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(json, ["test:a", "test:b"]);
+
+    // The listing can be piped into other commands:
+    let output = root.run_with_stdin(&["fetch", "-"], listing.as_bytes())?;
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&output), "fetched test:a\nfetched test:b\n");
+
+    Ok(())
+}
+
+#[test]
+pub fn test_module_install() -> Result<()> {
+    let root = Root::new()?;
+    write_module_source(&root, "example", "v1")?;
+
+    let output = root.run(&["module", "install", "example"])?;
+    assert!(output.status.success(), "{:?}", output);
+    assert!(root.path().join("modules/example.yaml").is_file());
+    assert!(root.path().join("libexec/asimov-example-fetcher").is_file());
+    assert_eq!(stdout(&root.run(&["fetch", "example:x"])?), "v1\n");
+
+    // An installed module is only replaced with `--force`:
+    write_module_source(&root, "example", "v2")?;
+    let output = root.run(&["module", "install", "example"])?;
+    assert!(!output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&root.run(&["fetch", "example:x"])?), "v1\n");
+
+    let output = root.run(&["module", "install", "--force", "example"])?;
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&root.run(&["fetch", "example:x"])?), "v2\n");
+
+    Ok(())
+}
+
+#[test]
+pub fn test_module_uninstall() -> Result<()> {
+    let root = Root::new()?;
+    write_module_source(&root, "example", "v1")?;
+    assert!(root
+        .run(&["module", "install", "example"])?
+        .status
+        .success());
+
+    let output = root.run(&["module", "uninstall", "example"])?;
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!root.path().join("modules/example.yaml").exists());
+    assert!(!root.path().join("libexec/asimov-example-fetcher").exists());
+    assert!(!stdout(&root.run(&["module", "list"])?).contains("example"));
+
+    let output = root.run(&["module", "uninstall", "example"])?;
+    assert!(!output.status.success(), "{:?}", output);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_module_disable_enable() -> Result<()> {
+    let root = Root::new()?;
+    write_module_source(&root, "example", "v1")?;
+    assert!(root
+        .run(&["module", "install", "example"])?
+        .status
+        .success());
+
+    let output = root.run(&["module", "disable", "example"])?;
+    assert!(output.status.success(), "{:?}", output);
+    assert!(stdout(&root.run(&["module", "list"])?).contains("(disabled)"));
+    assert!(!root.run(&["fetch", "example:x"])?.status.success());
+
+    let output = root.run(&["module", "enable", "example"])?;
+    assert!(output.status.success(), "{:?}", output);
+    let listing = stdout(&root.run(&["module", "list"])?);
+    assert!(listing.contains("example") && !listing.contains("(disabled)"));
+    assert_eq!(stdout(&root.run(&["fetch", "example:x"])?), "v1\n");
+
+    Ok(())
+}
+
+#[test]
+pub fn test_module_list_fresh_root() -> Result<()> {
+    let root = Root::new()?;
+    std::fs::remove_dir(root.path().join("modules"))?;
+
+    let output = root.run(&["module", "list"])?;
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&output), "");
+
+    Ok(())
+}
+
+#[test]
+pub fn test_module_sync_restore() -> Result<()> {
+    let root = Root::new()?;
+    write_module_source(&root, "example", "v1")?;
+    let manifest = root.work_dir().join("example/module.yaml");
+    let mut manifest_yaml = std::fs::read_to_string(&manifest)?;
+    manifest_yaml.push_str("version: 1.0.0\n");
+    std::fs::write(&manifest, manifest_yaml)?;
+
+    // A registry index with the module's archive next to it:
+    let archived = Command::new("tar")
+        .args(["-czf", "example-1.0.0.tar.gz", "example"])
+        .current_dir(root.work_dir())
+        .status()?;
+    assert!(archived.success());
+    std::fs::write(
+        root.work_dir().join("index.yaml"),
+        "modules:\n  - name: example\n    version: 1.0.0\n    url: example-1.0.0.tar.gz\n",
+    )?;
+
+    assert!(root
+        .run(&["module", "install", "example"])?
+        .status
+        .success());
+    let output = root.run(&["module", "freeze"])?;
+    assert!(output.status.success(), "{:?}", output);
+    assert!(root.work_dir().join("asimov.lock").is_file());
+
+    assert!(root
+        .run(&["module", "uninstall", "example"])?
+        .status
+        .success());
+    let output = root.run(&["module", "sync", "--check"])?;
+    assert!(!output.status.success(), "{:?}", output);
+
+    let output = root.run(&["module", "sync", "--registry", "index.yaml"])?;
+    assert!(output.status.success(), "{:?}", output);
+    assert!(root.run(&["module", "sync", "--check"])?.status.success());
+    assert_eq!(stdout(&root.run(&["fetch", "example:x"])?), "v1\n");
+
+    Ok(())
+}
+
+#[test]
+pub fn test_project_modules() -> Result<()> {
+    let root = Root::new()?;
+    root.add_module(
+        "example",
+        "handles:\n  url_protocols:\n    - example\n",
+        &[("fetcher", "echo global")],
+    )?;
+
+    let project = root.work_dir();
+    write_module(
+        &project.join(".asimov"),
+        "example",
+        "handles:\n  url_protocols:\n    - example\n",
+        &[("fetcher", "echo project")],
+    )?;
+    let subdir = project.join("src");
+    std::fs::create_dir_all(&subdir)?;
+
+    // The modules of a project are ignored until it's trusted:
+    let output = root
+        .asimov(&["fetch", "example:x"])
+        .current_dir(&subdir)
+        .output()?;
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&output), "global\n");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("trusted_projects"));
+
+    std::fs::write(
+        root.path().join("config.yaml"),
+        format!("trusted_projects:\n  - {}\n", project.display()),
+    )?;
+    let output = root
+        .asimov(&["fetch", "example:x"])
+        .current_dir(&subdir)
+        .output()?;
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&output), "project\n");
+
+    // The global module is still there, but overlaid:
+    let output = root.run(&["module", "list"])?;
+    assert_eq!(stdout(&output).matches("example").count(), 1);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_module_directories() -> Result<()> {
+    let root = Root::new()?;
+    let modules = root.path().join("modules");
+
+    // Manifests are discovered recursively:
+    write_module(
+        &root.path(),
+        "nested",
+        "handles:\n  url_protocols:\n    - nested\n",
+        &[("fetcher", "echo nested")],
+    )?;
+    std::fs::create_dir_all(modules.join("group"))?;
+    std::fs::rename(
+        modules.join("nested.yaml"),
+        modules.join("group/nested.yaml"),
+    )?;
+    assert_eq!(stdout(&root.run(&["fetch", "nested:x"])?), "nested\n");
+
+    // A module may have a directory of its own, along with its programs:
+    write_module_source(&root, "example", "v1")?;
+    std::fs::rename(root.work_dir().join("example"), modules.join("example"))?;
+    assert_eq!(stdout(&root.run(&["fetch", "example:x"])?), "v1\n");
+
+    let output = root.run(&["module", "uninstall", "example"])?;
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!modules.join("example").exists());
+    assert!(!root.run(&["fetch", "example:x"])?.status.success());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_module_install_replaces_module_directory() -> Result<()> {
+    let root = Root::new()?;
+    let modules = root.path().join("modules");
+    write_module_source(&root, "example", "v1")?;
+    std::fs::rename(root.work_dir().join("example"), modules.join("example"))?;
+
+    write_module_source(&root, "example", "v2")?;
+    let output = root.run(&["module", "install", "example"])?;
+    assert!(!output.status.success(), "{:?}", output);
+    assert_eq!(stdout(&root.run(&["fetch", "example:x"])?), "v1\n");
+
+    let output = root.run(&["module", "install", "--force", "example"])?;
+    assert!(output.status.success(), "{:?}", output);
+    assert!(!modules.join("example").exists());
+    assert!(modules.join("example.yaml").is_file());
+    assert_eq!(stdout(&root.run(&["fetch", "example:x"])?), "v2\n");
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+// This is free and unencumbered software released into the public domain.
+
+use asimov_cli::shared::{InstalledModule, ManifestExtensions, Requirements};
+use asimov_module::models::ModuleManifest;
+use std::path::PathBuf;
+
+fn module(version: Option<&str>, asimov_cli: Option<&str>) -> InstalledModule {
+    InstalledModule {
+        path: PathBuf::from("modules/example/module.yaml"),
+        manifest: ModuleManifest {
+            name: "example".to_string(),
+            ..Default::default()
+        },
+        extensions: ManifestExtensions {
+            version: version.map(String::from),
+            requires: Requirements {
+                asimov_cli: asimov_cli.map(String::from),
+            },
+            ..Default::default()
+        },
+    }
+}
+
+fn this_version() -> semver::Version {
+    semver::Version::parse(env!("CARGO_PKG_VERSION")).unwrap()
+}
+
+#[test]
+pub fn test_check_compatibility() {
+    let major = this_version().major;
+    assert!(module(None, None).check_compatibility().is_ok());
+    assert!(module(Some("1.2.0"), None).check_compatibility().is_ok());
+
+    let matching = format!(">={}.0.0, <{}.0.0", major, major + 1);
+    assert!(module(None, Some(&matching)).check_compatibility().is_ok());
+
+    let newer = format!(">={}.0.0", major + 1);
+    let error = module(None, Some(&newer))
+        .check_compatibility()
+        .unwrap_err();
+    assert!(error.contains("requires asimov-cli"));
+
+    let error = module(None, Some("not a range"))
+        .check_compatibility()
+        .unwrap_err();
+    assert!(error.contains("invalid asimov-cli version requirement"));
+}
+
+#[test]
+pub fn test_check_compatibility_invalid_version() {
+    let error = module(Some("1.x"), None).check_compatibility().unwrap_err();
+    assert!(error.contains("invalid module version `1.x`"));
+}
+
+#[test]
+pub fn test_check_compatibility_prerelease() {
+    // Development builds satisfy the requirements their release satisfies:
+    let version = this_version();
+    let release = format!("={}.{}.{}", version.major, version.minor, version.patch);
+    assert!(module(None, Some(&release)).check_compatibility().is_ok());
+
+    let older = format!("<{}.{}.{}", version.major, version.minor, version.patch);
+    assert!(module(None, Some(&older)).check_compatibility().is_err());
+}
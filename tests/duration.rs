@@ -0,0 +1,16 @@
+// This is free and unencumbered software released into the public domain.
+
+use asimov_cli::commands::parse_duration;
+use std::time::Duration;
+
+#[test]
+pub fn test_parse_duration() {
+    assert_eq!(parse_duration("2"), Ok(Duration::from_secs(2)));
+    assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+    assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+    assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+    assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+    assert!(parse_duration("").is_err());
+    assert!(parse_duration("s").is_err());
+    assert!(parse_duration("5 days").is_err());
+}
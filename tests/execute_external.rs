@@ -16,6 +16,8 @@ pub fn test_execute_external() -> Result<()> {
         let external_cmd = External {
             is_debug: false,
             pipe_output: true,
+            verbosity: 0,
+            ..Default::default()
         };
 
         let cd_name = file.name.trim_start_matches(TEST_PREFIX);
@@ -36,3 +38,79 @@ pub fn test_execute_external() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(unix)]
+#[test]
+pub fn test_execute_external_signal() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = shared::init()?;
+
+    let path = dir.child("asimov-terminated");
+    std::fs::write(&path, "#!/bin/sh\nkill -TERM $$\n")?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+
+    let external_cmd = External {
+        is_debug: false,
+        pipe_output: true,
+        verbosity: 0,
+        ..Default::default()
+    };
+
+    let result = external_cmd.execute("terminated", &[]).unwrap();
+    assert_eq!(result.status, 128 + 15);
+    assert_eq!(result.code, EX_SOFTWARE);
+
+    Ok(())
+}
+
+#[test]
+pub fn test_execute_external_max_output() -> Result<()> {
+    let _dir = shared::init()?;
+
+    let external_cmd = External {
+        is_debug: false,
+        pipe_output: true,
+        max_output: Some(5),
+        ..Default::default()
+    };
+
+    let result = external_cmd.execute("hello", &[]).unwrap();
+    assert_eq!(result.code, EX_OK);
+    assert!(result.truncated);
+    assert_eq!(result.stdout.unwrap(), b"Hello");
+
+    Ok(())
+}
+
+#[test]
+pub fn test_execute_external_tee() -> Result<()> {
+    // The output is copied to the actual stdout, which the test harness
+    // doesn't capture, so the test runs itself in a child process to see it:
+    if std::env::var_os("ASIMOV_TEST_TEE").is_some() {
+        let _dir = shared::init()?;
+
+        let external_cmd = External {
+            is_debug: false,
+            pipe_output: true,
+            tee: true,
+            max_output: Some(5),
+            ..Default::default()
+        };
+
+        let result = external_cmd.execute("hello", &[]).unwrap();
+        assert_eq!(result.code, EX_OK);
+        assert_eq!(result.stdout.unwrap(), b"Hello");
+        return Ok(());
+    }
+
+    let output = std::process::Command::new(std::env::current_exe()?)
+        .args(["--exact", "test_execute_external_tee", "--test-threads=1"])
+        .env("ASIMOV_TEST_TEE", "1")
+        .output()?;
+    assert!(output.status.success(), "{:?}", output);
+    // The whole output is copied, even beyond what's captured:
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Hello, world!\n"));
+
+    Ok(())
+}
@@ -1,7 +1,7 @@
 // This is free and unencumbered software released into the public domain.
 
 use asimov_cli::commands::HelpCmd;
-use clientele::SysexitsError::*;
+use clientele::{crates::clap::ColorChoice, SysexitsError::*};
 
 mod shared;
 use shared::{Result, TEST_FILES, TEST_PREFIX};
@@ -13,7 +13,10 @@ pub fn test_execute_help_cmd() -> Result<()> {
     for file in TEST_FILES.iter() {
         println!("{}: ", file.name);
 
-        let external_cmd = HelpCmd { is_debug: false };
+        let external_cmd = HelpCmd {
+            is_debug: false,
+            color: ColorChoice::Auto,
+        };
 
         let cd_name = file.name.trim_start_matches(TEST_PREFIX);
         let result = external_cmd.execute(cd_name, &[]);
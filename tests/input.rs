@@ -0,0 +1,50 @@
+// This is free and unencumbered software released into the public domain.
+
+use asimov_cli::input::{detect_media_type, file_media_type, normalize};
+use temp_dir::TempDir;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[test]
+pub fn test_normalize_file_path() -> Result<()> {
+    let dir = TempDir::new()?;
+    let path = dir.child("data.html");
+    std::fs::write(&path, "<!DOCTYPE html>")?;
+
+    let url = normalize(path.to_str().unwrap())?;
+    assert!(url.starts_with("file:///"));
+    assert!(url.ends_with("/data.html"));
+    assert_eq!(file_media_type(&url), Some("text/html"));
+
+    assert!(normalize("./does-not-exist.html").is_err());
+
+    Ok(())
+}
+
+#[test]
+pub fn test_detect_media_type_by_magic() -> Result<()> {
+    let dir = TempDir::new()?;
+    let path = dir.child("document");
+    std::fs::write(&path, "%PDF-1.7\n")?;
+
+    assert_eq!(detect_media_type(&path), Some("application/pdf"));
+
+    Ok(())
+}
+
+#[test]
+pub fn test_normalize_url() -> Result<()> {
+    assert_eq!(normalize("example.com/x")?, "https://example.com/x");
+    assert_eq!(normalize("https://example.com/x")?, "https://example.com/x");
+    assert_eq!(normalize("HTTPS://Example.COM")?, "https://example.com/");
+    assert_eq!(normalize("localhost:8080")?, "https://localhost:8080/");
+    assert_eq!(
+        normalize("https://bücher.de/%7euser?q=%3f")?,
+        "https://xn--bcher-kva.de/~user?q=%3F"
+    );
+
+    assert!(normalize("https://example.com:99999/").is_err());
+    assert!(normalize("https://exa mple.com/").is_err());
+
+    Ok(())
+}
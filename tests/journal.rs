@@ -0,0 +1,43 @@
+// This is free and unencumbered software released into the public domain.
+
+use asimov_cli::journal::Journal;
+use std::io::Result;
+use temp_dir::TempDir;
+
+#[test]
+pub fn test_journal_resume() -> Result<()> {
+    let dir = TempDir::new()?;
+    let path = dir.child("journal.jsonl");
+
+    let mut journal = Journal::open(&path, false)?;
+    journal.record("https://example.org/a", "example", 0)?;
+    journal.record("https://example.org/b", "example", 75)?;
+    journal.record("https://example.org/c", "example", 0)?;
+    journal.record("https://example.org/c", "example", 69)?;
+    drop(journal);
+
+    // Without resuming, nothing is considered completed:
+    let journal = Journal::open(&path, false)?;
+    assert!(!journal.is_completed("https://example.org/a"));
+    drop(journal);
+
+    // A partially written line is ignored:
+    std::fs::write(
+        &path,
+        std::fs::read_to_string(&path)? + r#"{"url":"https://example.org/d","mod"#,
+    )?;
+
+    let journal = Journal::open(&path, true)?;
+    assert!(journal.is_completed("https://example.org/a"));
+    assert!(!journal.is_completed("https://example.org/b"));
+    assert!(!journal.is_completed("https://example.org/c"));
+    assert!(!journal.is_completed("https://example.org/d"));
+
+    let mut journal = journal;
+    journal.record("https://example.org/d", "example", 0)?;
+    drop(journal);
+    let journal = Journal::open(&path, true)?;
+    assert!(journal.is_completed("https://example.org/d"));
+
+    Ok(())
+}
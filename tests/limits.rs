@@ -0,0 +1,80 @@
+// This is free and unencumbered software released into the public domain.
+
+use asimov_cli::limits::{exceeded, parse_size, LimitExceeded, Limits};
+
+#[test]
+pub fn test_parse_size() {
+    assert_eq!(parse_size("1024"), Ok(1024));
+    assert_eq!(parse_size("512K"), Ok(512 << 10));
+    assert_eq!(parse_size("512M"), Ok(512 << 20));
+    assert_eq!(parse_size("2GiB"), Ok(2 << 30));
+    assert_eq!(parse_size(" 1t "), Ok(1 << 40));
+    assert!(parse_size("").is_err());
+    assert!(parse_size("M").is_err());
+    assert!(parse_size("12X").is_err());
+}
+
+#[test]
+pub fn test_limits_min() {
+    let invocation = Limits {
+        memory: Some(512 << 20),
+        cpu_time: Some(60),
+        ..Default::default()
+    };
+    let module = Limits {
+        memory: Some(256 << 20),
+        open_files: Some(64),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        invocation.min(module),
+        Limits {
+            memory: Some(256 << 20),
+            cpu_time: Some(60),
+            open_files: Some(64),
+            output_size: None,
+        }
+    );
+    assert!(Limits::default().is_empty());
+    assert!(!invocation.is_empty());
+}
+
+#[test]
+pub fn test_limits_deserialize() {
+    let limits: Limits =
+        serde_yml::from_str("memory: 1G\ncpu_time: 30\noutput_size: 4096\n").unwrap();
+    assert_eq!(limits.memory, Some(1 << 30));
+    assert_eq!(limits.cpu_time, Some(30));
+    assert_eq!(limits.open_files, None);
+    assert_eq!(limits.output_size, Some(4096));
+}
+
+#[cfg(unix)]
+#[test]
+pub fn test_limits_exceeded() {
+    use std::{os::unix::process::ExitStatusExt, process::ExitStatus};
+
+    let limited = Limits {
+        memory: Some(1 << 30),
+        ..Default::default()
+    };
+    let unlimited = Limits::default();
+
+    let signaled = |signal| ExitStatus::from_raw(signal);
+    assert_eq!(
+        exceeded(&signaled(libc::SIGXCPU), &unlimited),
+        Some(LimitExceeded::CpuTime)
+    );
+    assert_eq!(
+        exceeded(&signaled(libc::SIGXFSZ), &unlimited),
+        Some(LimitExceeded::OutputSize)
+    );
+    assert_eq!(
+        exceeded(&signaled(libc::SIGABRT), &limited),
+        Some(LimitExceeded::Memory)
+    );
+    assert_eq!(exceeded(&signaled(libc::SIGSEGV), &unlimited), None);
+    assert_eq!(exceeded(&signaled(libc::SIGTERM), &limited), None);
+    assert_eq!(exceeded(&ExitStatus::from_raw(1 << 8), &limited), None);
+}
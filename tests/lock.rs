@@ -0,0 +1,52 @@
+// This is free and unencumbered software released into the public domain.
+
+use asimov_cli::lock::{Drift, LockedModule, Lockfile};
+use std::collections::BTreeMap;
+
+fn module(name: &str, version: &str, program_checksum: &str) -> LockedModule {
+    LockedModule {
+        name: name.to_string(),
+        version: Some(version.to_string()),
+        manifest: "0".repeat(64),
+        programs: BTreeMap::from([(
+            format!("asimov-{}-fetcher", name),
+            program_checksum.to_string(),
+        )]),
+    }
+}
+
+#[test]
+pub fn test_lockfile_diff() {
+    let locked = Lockfile {
+        modules: vec![
+            module("alpha", "1.0.0", "a"),
+            module("beta", "1.0.0", "b"),
+            module("gamma", "1.0.0", "c"),
+        ],
+    };
+    let installed = Lockfile {
+        modules: vec![
+            module("alpha", "1.0.0", "a"),
+            module("beta", "1.1.0", "x"),
+            module("delta", "1.0.0", "d"),
+        ],
+    };
+
+    assert!(locked.diff(&locked).is_empty());
+    assert_eq!(
+        locked.diff(&installed),
+        vec![
+            Drift::Version {
+                name: "beta".to_string(),
+                locked: Some("1.0.0".to_string()),
+                installed: Some("1.1.0".to_string()),
+            },
+            Drift::Program {
+                name: "beta".to_string(),
+                program: "asimov-beta-fetcher".to_string(),
+            },
+            Drift::Missing("gamma".to_string()),
+            Drift::Unlocked("delta".to_string()),
+        ]
+    );
+}
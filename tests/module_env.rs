@@ -0,0 +1,42 @@
+// This is free and unencumbered software released into the public domain.
+
+use asimov_cli::commands::External;
+use clientele::{crates::clap::ColorChoice, SysexitsError::*};
+
+#[allow(dead_code)]
+mod shared;
+use shared::Result;
+
+#[cfg(unix)]
+#[test]
+pub fn test_module_env() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = shared::init()?;
+
+    let path = dir.child("asimov-printenv");
+    std::fs::write(
+        &path,
+        "#!/bin/sh\necho \"$ASIMOV_CLI_VERSION $ASIMOV_VERBOSE $ASIMOV_DEBUG $ASIMOV_COLOR\"\n",
+    )?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+
+    let external_cmd = External {
+        is_debug: true,
+        pipe_output: true,
+        verbosity: 2,
+        color: ColorChoice::Always,
+        ..Default::default()
+    };
+
+    let result = external_cmd.execute("printenv", &[]).unwrap();
+    assert_eq!(result.code, EX_OK);
+
+    let stdout = result.stdout.unwrap();
+    assert_eq!(
+        std::str::from_utf8(&stdout).unwrap().trim(),
+        format!("{} 2 1 always", env!("CARGO_PKG_VERSION"))
+    );
+
+    Ok(())
+}
@@ -0,0 +1,51 @@
+// This is free and unencumbered software released into the public domain.
+
+#![cfg(feature = "module")]
+
+use asimov_cli::registry::Registry;
+use indoc::indoc;
+use temp_dir::TempDir;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+static INDEX: &str = indoc! {"
+    modules:
+      - name: example
+        version: 1.0.0
+        summary: An example module
+        url: example-1.0.0.tar.gz
+      - name: example
+        version: 1.2.0
+        summary: An example module
+        url: example-1.2.0.tar.gz
+        sha256: 3a6eb0790f39ac87c94f3856b2dd2c5d110e6811602261a9a923d3bb23adc8b7
+      - name: other
+        version: 0.1.0
+        url: other-0.1.0.tar.gz
+        sha256: 0000000000000000000000000000000000000000000000000000000000000000
+"};
+
+#[test]
+pub fn test_registry() -> Result<()> {
+    let dir = TempDir::new()?;
+    std::fs::write(dir.child("index.yaml"), INDEX)?;
+    std::fs::write(dir.child("example-1.2.0.tar.gz"), "data")?;
+    std::fs::write(dir.child("other-0.1.0.tar.gz"), "data")?;
+
+    let registry = Registry::load(dir.child("index.yaml").to_str().unwrap())?;
+    assert_eq!(registry.search("EXAMPLE").len(), 2);
+
+    let entry = registry.find("example", None).unwrap();
+    assert_eq!(entry.version, "1.2.0");
+    assert!(registry.find("example", Some("1.0.0")).is_some());
+    assert!(registry.find("example", Some("2.0.0")).is_none());
+
+    let downloads = TempDir::new()?;
+    let archive = registry.download(entry, downloads.path())?;
+    assert_eq!(std::fs::read(archive)?, b"data");
+
+    let entry = registry.find("other", None).unwrap();
+    assert!(registry.download(entry, downloads.path()).is_err());
+
+    Ok(())
+}
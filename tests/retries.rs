@@ -0,0 +1,69 @@
+// This is free and unencumbered software released into the public domain.
+
+use asimov_cli::commands::{with_retries, BatchOptions};
+use clientele::SysexitsError::{self, *};
+use std::time::Duration;
+
+fn batch(retries: u32) -> BatchOptions {
+    BatchOptions {
+        retries,
+        retry_backoff: Duration::ZERO,
+        ..Default::default()
+    }
+}
+
+fn is_retriable(code: SysexitsError) -> bool {
+    code == EX_TEMPFAIL
+}
+
+#[test]
+pub fn test_with_retries_until_success() {
+    let mut attempts = 0;
+    let result = with_retries(&batch(3), "https://example.org", is_retriable, || {
+        attempts += 1;
+        Ok(if attempts < 3 { EX_TEMPFAIL } else { EX_OK })
+    });
+    assert_eq!(result, Ok(EX_OK));
+    assert_eq!(attempts, 3);
+}
+
+#[test]
+pub fn test_with_retries_exhausted() {
+    let mut attempts = 0;
+    let result = with_retries(&batch(2), "https://example.org", is_retriable, || {
+        attempts += 1;
+        Ok(EX_TEMPFAIL)
+    });
+    assert_eq!(result, Ok(EX_TEMPFAIL));
+    assert_eq!(attempts, 3);
+
+    let mut attempts = 0;
+    let result = with_retries(&batch(0), "https://example.org", is_retriable, || {
+        attempts += 1;
+        Ok(EX_TEMPFAIL)
+    });
+    assert_eq!(result, Ok(EX_TEMPFAIL));
+    assert_eq!(attempts, 1);
+}
+
+#[test]
+pub fn test_with_retries_not_retriable() {
+    let mut attempts = 0;
+    let result = with_retries(&batch(3), "https://example.org", is_retriable, || {
+        attempts += 1;
+        Ok(EX_DATAERR)
+    });
+    assert_eq!(result, Ok(EX_DATAERR));
+    assert_eq!(attempts, 1);
+}
+
+#[test]
+pub fn test_with_retries_spawn_error() {
+    let mut attempts = 0;
+    let result = with_retries(&batch(3), "https://example.org", is_retriable, || {
+        attempts += 1;
+        Err(EX_TEMPFAIL)
+    });
+    assert_eq!(result, Err(EX_TEMPFAIL));
+    assert_eq!(attempts, 1);
+}
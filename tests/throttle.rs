@@ -0,0 +1,86 @@
+// This is free and unencumbered software released into the public domain.
+
+use asimov_cli::throttle::{
+    acquire_local, RateLimits, DEFAULT_CONCURRENCY, DEFAULT_REQUESTS_PER_SECOND,
+};
+use std::time::{Duration, Instant};
+
+#[test]
+pub fn test_rate_limits_min() {
+    let configured = RateLimits {
+        concurrency: Some(8),
+        requests_per_second: Some(0.0),
+    };
+    let module = RateLimits {
+        concurrency: Some(1),
+        requests_per_second: Some(2.0),
+    };
+    assert_eq!(
+        configured.min(module),
+        RateLimits {
+            concurrency: Some(1),
+            requests_per_second: Some(2.0),
+        }
+    );
+
+    let unlimited = RateLimits {
+        concurrency: Some(0),
+        requests_per_second: None,
+    };
+    assert_eq!(
+        unlimited.min(configured),
+        RateLimits {
+            concurrency: Some(8),
+            requests_per_second: Some(0.0),
+        }
+    );
+}
+
+#[test]
+pub fn test_rate_limits_or_default() {
+    assert_eq!(
+        RateLimits::default().or_default(),
+        RateLimits {
+            concurrency: Some(DEFAULT_CONCURRENCY),
+            requests_per_second: Some(DEFAULT_REQUESTS_PER_SECOND),
+        }
+    );
+    let configured = RateLimits {
+        concurrency: Some(0),
+        requests_per_second: None,
+    };
+    assert_eq!(configured.or_default().concurrency, Some(0));
+}
+
+#[test]
+pub fn test_rate_limits_deserialize() {
+    let limits: RateLimits =
+        serde_yml::from_str("concurrency: 4\nrequests_per_second: 0.5\n").unwrap();
+    assert_eq!(limits.concurrency, Some(4));
+    assert_eq!(limits.requests_per_second, Some(0.5));
+
+    let unlimited: RateLimits = serde_yml::from_str("requests_per_second: 0\n").unwrap();
+    assert_eq!(unlimited.requests_per_second, Some(0.0));
+
+    assert!(serde_yml::from_str::<RateLimits>("requests_per_second: -1\n").is_err());
+    assert!(serde_yml::from_str::<RateLimits>("requests_per_second: .inf\n").is_err());
+    assert!(serde_yml::from_str::<RateLimits>("requests_per_second: .nan\n").is_err());
+}
+
+#[test]
+pub fn test_acquire_local() {
+    let limits = RateLimits {
+        concurrency: Some(1),
+        requests_per_second: Some(20.0),
+    };
+    let start = Instant::now();
+    drop(acquire_local("https://local.example.org/a", &limits));
+    drop(acquire_local("https://local.example.org/b", &limits));
+    assert!(start.elapsed() >= Duration::from_millis(45));
+
+    // URLs without a host aren't throttled:
+    let start = Instant::now();
+    drop(acquire_local("file:///tmp/a", &limits));
+    drop(acquire_local("file:///tmp/b", &limits));
+    assert!(start.elapsed() < Duration::from_millis(45));
+}
@@ -0,0 +1,25 @@
+// This is free and unencumbered software released into the public domain.
+
+use asimov_cli::verify::{sha256_file, verify_file, VerifyError};
+use temp_dir::TempDir;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+static DATA_SHA256: &str = "3a6eb0790f39ac87c94f3856b2dd2c5d110e6811602261a9a923d3bb23adc8b7";
+
+#[test]
+pub fn test_verify_file() -> Result<()> {
+    let dir = TempDir::new()?;
+    let path = dir.child("asimov-example-fetcher");
+    std::fs::write(&path, "data")?;
+
+    assert_eq!(sha256_file(&path)?, DATA_SHA256);
+    assert!(verify_file(&path, Some(DATA_SHA256), &[])?);
+    assert!(!verify_file(&path, None, &[])?);
+    assert!(matches!(
+        verify_file(&path, Some(&"0".repeat(64)), &[]),
+        Err(VerifyError::ChecksumMismatch { .. })
+    ));
+
+    Ok(())
+}